@@ -0,0 +1,204 @@
+// Copyright 2024 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Chromium specific commands via the Chrome DevTools Protocol (CDP).
+//! chromedriver forwards these commands to the browser with the vendor
+//! specific goog/cdp/execute endpoint. The browser log is retrieved with the
+//! chromedriver specific se/log endpoint.
+
+use fantoccini::wd::WebDriverCompatibleCommand;
+use fantoccini::Client;
+use log::debug;
+use serde_json::{json, Value};
+
+/// a CDP command with its parameters
+#[derive(Debug)]
+struct CdpCommand {
+    /// the CDP method, e.g. Network.setExtraHTTPHeaders
+    cmd: String,
+    /// the parameters of the method
+    params: Value,
+}
+
+impl WebDriverCompatibleCommand for CdpCommand {
+    fn endpoint(
+        &self,
+        base_url: &url::Url,
+        session_id: Option<&str>,
+    ) -> Result<url::Url, url::ParseError> {
+        base_url.join(&format!(
+            "session/{}/goog/cdp/execute",
+            session_id.unwrap_or_default()
+        ))
+    }
+
+    fn method_and_body(
+        &self,
+        _request_url: &url::Url,
+    ) -> (http::Method, Option<String>) {
+        let body = json!({ "cmd": self.cmd, "params": self.params });
+        (http::Method::POST, Some(body.to_string()))
+    }
+}
+
+/// execute the CDP method with the given parameters in the browser of the
+/// webclient. fails if the browser does not support CDP
+pub async fn execute(
+    webclient: &Client,
+    cmd: &str,
+    params: Value,
+) -> anyhow::Result<Value> {
+    debug!("execute cdp command {} with {}", cmd, params);
+    let result =
+        webclient.issue_cmd(CdpCommand { cmd: cmd.to_string(), params }).await?;
+    Ok(result)
+}
+
+/// send the given headers with every request of the browser
+pub async fn set_extra_http_headers(
+    webclient: &Client,
+    headers: &[(String, String)],
+) -> anyhow::Result<()> {
+    let headers: serde_json::Map<String, Value> = headers
+        .iter()
+        .map(|(name, value)| (name.clone(), Value::from(value.clone())))
+        .collect();
+    execute(webclient, "Network.enable", json!({})).await?;
+    execute(webclient, "Network.setExtraHTTPHeaders", json!({ "headers": headers }))
+        .await?;
+    Ok(())
+}
+
+/// override the user agent string the browser reports to the pages
+pub async fn set_user_agent(
+    webclient: &Client,
+    user_agent: &str,
+) -> anyhow::Result<()> {
+    execute(
+        webclient,
+        "Network.setUserAgentOverride",
+        json!({ "userAgent": user_agent }),
+    )
+    .await?;
+    Ok(())
+}
+
+/// override the locale of the browser, e.g. for the formatting of dates and
+/// numbers with the Intl API
+pub async fn set_locale(webclient: &Client, locale: &str) -> anyhow::Result<()> {
+    execute(webclient, "Emulation.setLocaleOverride", json!({ "locale": locale }))
+        .await?;
+    Ok(())
+}
+
+/// override the timezone of the browser with an IANA timezone id, e.g.
+/// Europe/Berlin
+pub async fn set_timezone(webclient: &Client, timezone: &str) -> anyhow::Result<()> {
+    execute(
+        webclient,
+        "Emulation.setTimezoneOverride",
+        json!({ "timezoneId": timezone }),
+    )
+    .await?;
+    Ok(())
+}
+
+/// emulate the prefers-reduced-motion: reduce media feature, so pages that
+/// respect it disable their animations and transitions
+pub async fn emulate_reduced_motion(webclient: &Client) -> anyhow::Result<()> {
+    execute(
+        webclient,
+        "Emulation.setEmulatedMedia",
+        json!({
+            "features": [{ "name": "prefers-reduced-motion", "value": "reduce" }],
+        }),
+    )
+    .await?;
+    Ok(())
+}
+
+/// slow down the CPU of the browser by the given factor, 1 disables the
+/// throttling
+pub async fn set_cpu_throttling_rate(
+    webclient: &Client,
+    rate: f64,
+) -> anyhow::Result<()> {
+    execute(webclient, "Emulation.setCPUThrottlingRate", json!({ "rate": rate }))
+        .await?;
+    Ok(())
+}
+
+/// emulate a slow network connection with the given latency in milliseconds
+/// and throughput in bytes per second
+pub async fn emulate_network_conditions(
+    webclient: &Client,
+    latency: f64,
+    download_throughput: f64,
+    upload_throughput: f64,
+) -> anyhow::Result<()> {
+    execute(webclient, "Network.enable", json!({})).await?;
+    execute(
+        webclient,
+        "Network.emulateNetworkConditions",
+        json!({
+            "offline": false,
+            "latency": latency,
+            "downloadThroughput": download_throughput,
+            "uploadThroughput": upload_throughput,
+        }),
+    )
+    .await?;
+    Ok(())
+}
+
+/// retrieve the browser log entries with the chromedriver log endpoint.
+/// Retrieving the entries removes them from the log
+#[derive(Debug)]
+struct BrowserLogCommand;
+
+impl WebDriverCompatibleCommand for BrowserLogCommand {
+    fn endpoint(
+        &self,
+        base_url: &url::Url,
+        session_id: Option<&str>,
+    ) -> Result<url::Url, url::ParseError> {
+        base_url.join(&format!("session/{}/se/log", session_id.unwrap_or_default()))
+    }
+
+    fn method_and_body(
+        &self,
+        _request_url: &url::Url,
+    ) -> (http::Method, Option<String>) {
+        (http::Method::POST, Some(json!({ "type": "browser" }).to_string()))
+    }
+}
+
+/// the capability that enables collecting the browser log in chromedriver
+pub fn browser_log_capability() -> (String, Value) {
+    (String::from("goog:loggingPrefs"), json!({ "browser": "ALL" }))
+}
+
+/// return the messages of all severe browser log entries since the last
+/// call, e.g. uncaught exceptions and console.error calls
+pub async fn take_console_errors(webclient: &Client) -> anyhow::Result<Vec<String>> {
+    let entries = webclient.issue_cmd(BrowserLogCommand).await?;
+    let errors = entries
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry["level"] == "SEVERE")
+        .filter_map(|entry| entry["message"].as_str().map(str::to_string))
+        .collect();
+    Ok(errors)
+}
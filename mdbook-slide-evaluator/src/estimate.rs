@@ -0,0 +1,426 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A crude estimation of the size of a slide from its html, for
+//! environments without a browser. The text of the content element is split
+//! into blocks (paragraphs, headings, list items and code blocks) that are
+//! wrapped at a fixed width with fixed character metrics. Styles, images and
+//! tables are not taken into account, the results are only a rough signal.
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use crate::evaluator::ElementSize;
+
+/// the font metrics and the content width used for the estimation
+#[derive(Debug, Clone)]
+pub struct LayoutMetrics {
+    /// the width at which text is wrapped in pixels
+    pub content_width: f64,
+    /// the font size of the body text in pixels
+    pub font_size: f64,
+    /// the line height as a multiple of the font size
+    pub line_height: f64,
+    /// the average width of a character as a multiple of the font size
+    pub char_width: f64,
+    /// the width of a character of a monospace font as a multiple of the
+    /// font size
+    pub code_char_width: f64,
+    /// the space between blocks as a multiple of the font size
+    pub block_spacing: f64,
+}
+
+impl Default for LayoutMetrics {
+    /// roughly the metrics of the default mdbook theme at the default
+    /// slide width
+    fn default() -> Self {
+        LayoutMetrics {
+            content_width: 750.0,
+            font_size: 20.0,
+            line_height: 1.45,
+            char_width: 0.5,
+            code_char_width: 0.6,
+            block_spacing: 1.0,
+        }
+    }
+}
+
+/// the estimated size of the content element of a slide
+#[derive(Debug)]
+pub struct LayoutEstimate {
+    /// the estimated size of the element. Its position is always (0, 0)
+    pub element_size: ElementSize,
+    /// the number of lines of the longest code block
+    pub max_code_lines: usize,
+}
+
+/// a step of an element path: elements with the tag name, or any element
+/// if none, that have the id if provided
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    name: Option<String>,
+    id: Option<String>,
+}
+
+impl Step {
+    /// parse a step like `main`, `*[@id="content"]` or `div[@id='a']`
+    fn parse(step: &str) -> Option<Step> {
+        let (name, predicate) = match step.split_once('[') {
+            Some((name, predicate)) => (name, Some(predicate.strip_suffix(']')?)),
+            None => (step, None),
+        };
+        let name = match name {
+            "*" => None,
+            name if !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') =>
+            {
+                Some(name.to_ascii_lowercase())
+            }
+            _ => return None,
+        };
+        let id = match predicate {
+            Some(predicate) => {
+                let value = predicate.strip_prefix("@id=")?;
+                let quote =
+                    value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+                let id = value[1..].strip_suffix(quote)?;
+                Some(id.to_string())
+            }
+            None => None,
+        };
+        Some(Step { name, id })
+    }
+
+    /// true if the opening tag is an element of the step
+    fn matches(&self, tag: &str) -> bool {
+        self.name.as_ref().is_none_or(|name| parse_tag(tag).0 == *name)
+            && self.id.as_ref().is_none_or(|id| has_id(tag, id))
+    }
+}
+
+/// the path to the evaluated element in the subset of XPath that can be
+/// estimated without a browser: steps of a tag name or `*` with an optional
+/// id predicate, e.g. the default `//*[@id="content"]/main`. Child steps are
+/// matched like descendant steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementPath {
+    steps: Vec<Step>,
+}
+
+impl FromStr for ElementPath {
+    type Err = anyhow::Error;
+
+    fn from_str(xpath: &str) -> anyhow::Result<Self> {
+        let steps = xpath
+            .strip_prefix('/')
+            .map(|path| {
+                path.split('/')
+                    .filter(|step| !step.is_empty())
+                    .map(Step::parse)
+                    .collect::<Option<Vec<_>>>()
+            })
+            .unwrap_or_default()
+            .filter(|steps| !steps.is_empty())
+            .ok_or_else(|| {
+                anyhow!(
+                    "cannot estimate the element {xpath}, only tag names and \
+                     id predicates are supported"
+                )
+            })?;
+        Ok(ElementPath { steps })
+    }
+}
+
+/// a block of text that starts on a new line
+#[derive(Debug, PartialEq)]
+enum Block {
+    /// wrapped text with the font scaled by the factor, e.g. for headings
+    Text { chars: usize, scale: f64 },
+    /// preformatted code with the number of characters of each line
+    Code { lines: Vec<usize> },
+}
+
+/// tags that start a new block
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+/// the font scale of the headings of the default mdbook theme
+fn heading_scale(tag: &str) -> f64 {
+    match tag {
+        "h1" => 2.0,
+        "h2" => 1.5,
+        "h3" => 1.17,
+        _ => 1.0,
+    }
+}
+
+/// the name of the tag (lowercase) and whether it is a closing tag
+fn parse_tag(tag: &str) -> (String, bool) {
+    let (tag, closing) = match tag.strip_prefix('/') {
+        Some(tag) => (tag, true),
+        None => (tag, false),
+    };
+    let name = tag
+        .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    (name, closing)
+}
+
+/// true if the opening tag has the id
+fn has_id(tag: &str, id: &str) -> bool {
+    tag.contains(&format!("id=\"{id}\"")) || tag.contains(&format!("id='{id}'"))
+}
+
+/// the inner html of the first element whose opening tag satisfies the
+/// predicate. None if there is no such element
+fn inner_html(html: &str, predicate: impl Fn(&str) -> bool) -> Option<&str> {
+    let mut offset = 0;
+    let (name, content_start) = loop {
+        let start = offset + html[offset..].find('<')?;
+        let end = start + html[start..].find('>')?;
+        let tag = &html[start + 1..end];
+        offset = end + 1;
+        let (name, closing) = parse_tag(tag);
+        if !closing && predicate(tag) {
+            break (name, offset);
+        }
+    };
+    // find the matching closing tag, elements of the same name may be nested
+    let mut depth = 1;
+    offset = content_start;
+    while let Some(start) = html[offset..].find('<').map(|start| offset + start) {
+        let Some(end) = html[start..].find('>').map(|end| start + end) else {
+            break;
+        };
+        let tag = &html[start + 1..end];
+        let (tag_name, closing) = parse_tag(tag);
+        if tag_name == name && !tag.ends_with('/') {
+            depth = if closing { depth - 1 } else { depth + 1 };
+            if depth == 0 {
+                return Some(&html[content_start..start]);
+            }
+        }
+        offset = end + 1;
+    }
+    Some(&html[content_start..])
+}
+
+/// split the html into blocks of text. Scripts and styles are left out,
+/// whitespace is collapsed outside of code blocks and entities count as a
+/// single character
+fn text_blocks(html: &str) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut text = String::new();
+    let mut scale = 1.0;
+    let mut in_pre = false;
+    let flush =
+        |text: &mut String, in_pre: bool, scale: f64, blocks: &mut Vec<Block>| {
+            if in_pre {
+                let code = text.trim_matches('\n');
+                if !code.is_empty() {
+                    let lines =
+                        code.lines().map(|line| line.chars().count()).collect();
+                    blocks.push(Block::Code { lines });
+                }
+            } else {
+                let chars = text
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .chars()
+                    .count();
+                if chars > 0 {
+                    blocks.push(Block::Text { chars, scale });
+                }
+            }
+            text.clear();
+        };
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(tag_start) = rest.strip_prefix('<') {
+            let Some(end) = tag_start.find('>') else {
+                break;
+            };
+            let (name, closing) = parse_tag(&tag_start[..end]);
+            rest = &tag_start[end + 1..];
+            if !closing && (name == "script" || name == "style") {
+                let closing_tag = format!("</{name}");
+                rest = rest.find(&closing_tag).map_or("", |end| &rest[end..]);
+                continue;
+            }
+            if BLOCK_TAGS.contains(&name.as_str()) {
+                flush(&mut text, in_pre, scale, &mut blocks);
+                match (name.as_str(), closing) {
+                    ("pre", _) => in_pre = !closing,
+                    (heading, false) => scale = heading_scale(heading),
+                    (_, true) => scale = 1.0,
+                }
+            }
+            continue;
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        if c == '&' {
+            if let Some(end) = rest.find(';').filter(|end| *end <= 10) {
+                text.push('x');
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    flush(&mut text, in_pre, scale, &mut blocks);
+    blocks
+}
+
+/// estimate the size of the element of the html page, or of the element
+/// with the id fragment inside it if provided. None if the element is not
+/// found
+pub fn estimate_layout(
+    html: &str,
+    element: &ElementPath,
+    fragment: Option<&str>,
+    metrics: &LayoutMetrics,
+) -> Option<LayoutEstimate> {
+    let mut content = html;
+    for step in &element.steps {
+        content = inner_html(content, |tag| step.matches(tag))?;
+    }
+    let content = match fragment {
+        Some(fragment) => inner_html(content, |tag| has_id(tag, fragment))?,
+        None => content,
+    };
+
+    let (mut width, mut height, mut max_code_lines) = (0.0_f64, 0.0, 0);
+    for block in text_blocks(content) {
+        let (block_width, block_height) = match block {
+            Block::Text { chars, scale } => {
+                let font_size = metrics.font_size * scale;
+                let text_width = chars as f64 * metrics.char_width * font_size;
+                let lines = (text_width / metrics.content_width).ceil().max(1.0);
+                (
+                    text_width.min(metrics.content_width),
+                    lines * metrics.line_height * font_size,
+                )
+            }
+            Block::Code { lines } => {
+                max_code_lines = max_code_lines.max(lines.len());
+                let longest_line = lines.iter().copied().max().unwrap_or_default();
+                (
+                    longest_line as f64
+                        * metrics.code_char_width
+                        * metrics.font_size,
+                    lines.len() as f64 * metrics.line_height * metrics.font_size,
+                )
+            }
+        };
+        if height > 0.0 {
+            height += metrics.block_spacing * metrics.font_size;
+        }
+        width = width.max(block_width);
+        height += block_height;
+    }
+    Some(LayoutEstimate {
+        element_size: ElementSize { x: 0.0, y: 0.0, width, height },
+        max_code_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_blocks_of_slide() {
+        let html = "<h1>Intro</h1>\n<p>Hello   &amp; <em>welcome</em></p>
+<script>let a = '<p>';</script>
+<pre><code>fn main() {
+    println!(\"hi\");
+}
+</code></pre>";
+        assert_eq!(
+            text_blocks(html),
+            vec![
+                Block::Text { chars: 5, scale: 2.0 },
+                Block::Text { chars: 15, scale: 1.0 },
+                Block::Code { lines: vec![11, 19, 1] },
+            ]
+        );
+    }
+
+    #[test]
+    fn estimate_section_inside_main() {
+        let html = "<html><body><nav><p>menu</p></nav><main>
+<section id=\"a\"><div><p>short</p></div></section>
+<section id=\"b\"><pre>1\n2\n3\n4</pre></section>
+</main></body></html>";
+        let metrics = LayoutMetrics::default();
+        let main: ElementPath = "//main".parse().unwrap();
+        let estimate = estimate_layout(html, &main, Some("a"), &metrics).unwrap();
+        assert_eq!(estimate.element_size.width, 50.0);
+        assert_eq!(estimate.element_size.height, 29.0);
+        assert_eq!(estimate.max_code_lines, 0);
+
+        let estimate = estimate_layout(html, &main, None, &metrics).unwrap();
+        assert_eq!(estimate.element_size.height, 29.0 + 20.0 + 4.0 * 29.0);
+        assert_eq!(estimate.max_code_lines, 4);
+        assert!(estimate_layout(html, &main, Some("c"), &metrics).is_none());
+    }
+
+    #[test]
+    fn element_paths_of_tag_names_and_ids() {
+        let html = "<html><body><div id='content'><main><p>main</p></main></div>
+<article><p>article text</p></article></body></html>";
+        let metrics = LayoutMetrics::default();
+        let estimate_width = |xpath: &str| {
+            let element = xpath.parse().unwrap();
+            estimate_layout(html, &element, None, &metrics)
+                .map(|estimate| estimate.element_size.width)
+        };
+        assert_eq!(estimate_width(r#"//*[@id="content"]/main"#), Some(40.0));
+        assert_eq!(estimate_width("/html/body/article"), Some(120.0));
+        assert_eq!(estimate_width("//div[@id='other']"), None);
+
+        for xpath in ["main", "//main[1]", "//p[@class='a']", "//p/text()", "//"] {
+            assert!(xpath.parse::<ElementPath>().is_err(), "{xpath}");
+        }
+    }
+}
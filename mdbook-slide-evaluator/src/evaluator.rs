@@ -12,54 +12,187 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use std::io::Write as _;
-use std::path::{Path, PathBuf};
+use std::io::{self, Write as _};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context as _};
+use base64::Engine as _;
 use fantoccini::elements::Element;
+use fantoccini::wd::{Capabilities, TimeoutConfiguration};
 use fantoccini::Client;
-use log::{debug, warn};
-use serde::Serialize;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
 use strum::Display;
+use tokio::time::Instant;
 use tokio_util::sync::CancellationToken;
 use url::Url;
 
+use crate::cdp;
+use crate::estimate::{estimate_layout, ElementPath, LayoutMetrics};
+use crate::file_server::FileServer;
+use crate::metrics::{MetricRegistry, SlideMetric};
+use crate::pdf::{ContactSheetPage, ContactSheetWriter};
 use crate::slides::{Book, Slide};
 
+/// the webdriver session that is used by the evaluator
+pub enum WebdriverSession {
+    /// create a new session at the webdriver url with the given capabilities
+    New { webdriver: String, capabilities: Capabilities },
+    /// attach to an existing session with the given id at the webdriver url.
+    /// the session is kept open when the evaluator is done
+    Existing { webdriver: String, session_id: String },
+    /// use an already established webclient. the session is kept open when
+    /// the evaluator is done
+    Client(Client),
+}
+
+impl WebdriverSession {
+    /// create the webclient for this session. returns the webclient and
+    /// whether the evaluator owns the session and has to close it
+    async fn connect(self) -> anyhow::Result<(Client, bool)> {
+        match self {
+            WebdriverSession::New { webdriver, capabilities } => {
+                debug!("connect to webdriver {} with {:?}", webdriver, capabilities);
+                let webclient = fantoccini::ClientBuilder::native()
+                    .capabilities(capabilities)
+                    .connect(&webdriver)
+                    .await?;
+                Ok((webclient, true))
+            }
+            WebdriverSession::Existing { webdriver, session_id } => {
+                debug!(
+                    "attach to session {} at webdriver {}",
+                    session_id, webdriver
+                );
+                let webclient = Client::with_existing_session(
+                    &webdriver,
+                    &session_id,
+                    hyper_tls::HttpsConnector::new(),
+                )
+                .await?;
+                // do not shut down the session once the webclient is dropped
+                webclient.persist().await?;
+                Ok((webclient, false))
+            }
+            WebdriverSession::Client(webclient) => Ok((webclient, false)),
+        }
+    }
+}
+
 /// An Evaluator is used to render a book that is a collection of slides
 /// and extract information from an element on that page. It further can
 /// take a screenshot of this element and store it. A webclient instance is
-/// created on creation and dropped once the Evaluator is dropped.
+/// created on creation and has to be closed with close once the
+/// Evaluator is not needed anymore.
 pub struct Evaluator<'a> {
     /// webclient used to render html
     webclient: Client,
-    /// selector for the element that is scored
-    element_selector: fantoccini::wd::Locator<'a>,
+    /// whether the session was created by the evaluator and has to be closed
+    owns_session: bool,
+    /// serves source_dir to the browser if enabled. Slides are opened
+    /// relative to its base url
+    file_server: Option<FileServer>,
+    /// XPath to the element that is scored
+    element_selector: &'a str,
     /// store screenshot in this directory if provided
     screenshot_dir: Option<PathBuf>,
+    /// draw the maximum slide size into the screenshots
+    screenshot_overlay: bool,
+    /// scale the screenshots from device pixels down to css pixels
+    normalize_dpr: bool,
+    /// flag slides whose screenshot is (nearly) a single color
+    detect_blank: bool,
+    /// measure the content box of the element without its padding in
+    /// addition to its rectangle
+    measure_content_box: bool,
+    /// how the paths of the output files are derived from the slide paths
+    output_layout: OutputLayout,
+    /// store the html of each evaluated slide in this directory if provided
+    html_dump_dir: Option<PathBuf>,
+    /// store the live DOM of each evaluated slide after rendering in this
+    /// directory if provided
+    dom_dump_dir: Option<PathBuf>,
     /// html base uri to the source_dir used as a prefix for each page
     html_base_url: Url,
-    /// base directory for all processed files
+    /// base directory for all processed files. the output files mirror the
+    /// layout of the slides relative to it
     source_dir: PathBuf,
     /// if this token is cancelled, the process needs to end gracefully
     cancellation_token: CancellationToken,
     /// the policy applied to the slides
     slide_policy: SlidePolicy,
+    /// evaluate at most this many slides if provided
+    max_slides: Option<usize>,
+    /// evaluate a slide up to this many more times if the evaluation fails
+    max_retries: usize,
+    /// how console errors of the slides are handled
+    console_error_policy: ConsoleErrorPolicy,
+    /// how slides without the evaluated element are handled
+    missing_element_policy: MissingElementPolicy,
+    /// elements with a smaller area in square pixels count as not found
+    min_element_area: f64,
+    /// switch to this frame before looking for the element if provided
+    frame: Option<Frame>,
+    /// measure each slide twice and flag differences above this tolerance
+    /// in pixels if provided
+    stability_tolerance: Option<f64>,
+    /// measure the element with an injected script instead of the webdriver
+    /// rectangle command
+    script_measurement: bool,
+    /// wait this long after opening a slide before measuring it if provided
+    settle_delay: Option<Duration>,
+    /// emulate prefers-reduced-motion: reduce in the browser
+    reduced_motion: bool,
+    /// the cpu slowdown factor emulated by the browser if provided
+    cpu_throttle: Option<f64>,
+    /// the network connection emulated by the browser if provided
+    network_throttle: Option<NetworkThrottle>,
+    /// zoom the pages by this factor before measuring if provided
+    zoom: Option<f64>,
+    /// javascript executed in each slide before measuring if provided
+    setup_script: Option<String>,
+    /// javascript that reveals the next fragment of a slide and returns true,
+    /// or returns false if all fragments are revealed, if provided
+    fragment_script: Option<String>,
+    /// css added to each slide before measuring if provided
+    extra_css: Option<String>,
+    /// web fonts loaded into each slide before measuring
+    fonts: Vec<WebFont>,
+    /// wait for the document to be completely loaded before measuring
+    wait_for_ready_state: bool,
+    /// the custom metrics measured for each slide
+    metrics: MetricRegistry,
+    /// append each result to this jsonl file as soon as it is measured if
+    /// provided
+    incremental_export: Option<PathBuf>,
+    /// the date the evaluator was created (YYYY-MM-DD) for the dated output
+    /// layout
+    run_date: String,
 }
 
 /// element coordinates returned by the browser
 #[derive(Debug)]
-struct ElementSize {
+pub struct ElementSize {
+    /// the horizontal position of the element relative to the viewport
+    pub x: f64,
+    /// the vertical position of the element relative to the viewport
+    pub y: f64,
     /// the width of the element
-    width: f64,
+    pub width: f64,
     /// the height of the element
-    height: f64,
+    pub height: f64,
 }
 
 impl From<(f64, f64, f64, f64)> for ElementSize {
     fn from(value: (f64, f64, f64, f64)) -> Self {
-        Self { width: value.2, height: value.3 }
+        Self { x: value.0, y: value.1, width: value.2, height: value.3 }
     }
 }
 
@@ -70,258 +203,4388 @@ pub struct EvaluationResult {
     slide: Slide,
     /// the size of the main content element
     element_size: ElementSize,
+    /// the scroll height of the whole page. A page that is much higher than
+    /// the element hints at content outside of the element
+    page_scroll_height: f64,
+    /// the device pixel ratio of the browser when the slide was measured
+    device_pixel_ratio: f64,
+    /// the font size of the root element in pixels (the size of 1rem)
+    root_font_size: f64,
+    /// the time it took to load and render the page of the slide, none if
+    /// the browser has no navigation timing for the page
+    render_time: Option<Duration>,
+    /// the number of lines of the longest code block in the element
+    max_code_lines: usize,
+    /// the number of elements in the element whose text is cut off, e.g.
+    /// with an ellipsis
+    clipped_elements: usize,
+    /// the number of fragments revealed with the fragment script. The
+    /// element size is the largest size across all fragments
+    fragment_steps: usize,
+    /// the maximum height applied to the slide instead of the one of the
+    /// slide policy because of a class of the element if any
+    max_height_override: Option<usize>,
+    /// the width and height of the content box of the element without
+    /// padding, borders and scrollbars if measured
+    content_box: Option<(f64, f64)>,
+    /// the name and value of each custom metric
+    metrics: Vec<(String, f64)>,
+    /// the amount the element exceeds the policy in each direction
+    overflow: Overflow,
+    /// the path of the screenshot relative to screenshot_dir if taken
+    screenshot: Option<PathBuf>,
+    /// true if the element exceeds the viewport and the screenshot may only
+    /// show the visible part of it
+    screenshot_clipped: bool,
+    /// errors logged by the browser while rendering the slide
+    console_errors: Vec<String>,
     /// all policy violations
     policy_violations: Vec<PolicyViolation>,
+    /// how much of the maximum slide size is filled by the element
+    fill_ratio: f64,
+    /// the severity derived from the fill ratio and the policy violations
+    severity: Severity,
+    /// true if a second measurement differed from the first one
+    unstable: bool,
+    /// true if the screenshot of the element is (nearly) a single color,
+    /// e.g. because styles or assets failed to load
+    blank: bool,
+    /// whether the style sheet of the extra css is enabled and still has
+    /// the rules the browser parsed when it was added, none if no extra css
+    /// was provided or the style sheet cannot be inspected
+    extra_css_applied: Option<bool>,
+    /// how often the evaluation of the slide was retried
+    retries: usize,
+    /// true if the size was estimated from the html instead of measured in
+    /// a browser
+    estimated: bool,
+    /// the XPath that matched the element, none if no element was found
+    element_selector: Option<Arc<str>>,
+}
+
+/// the severity of an evaluation result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Display, Serialize)]
+pub enum Severity {
+    /// the slide fits comfortably
+    Pass,
+    /// the slide fits but fills more than the warn threshold
+    Warn,
+    /// the slide violates the slide policy
+    Fail,
+}
+
+/// the frame of the page that contains the evaluated element
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Frame {
+    /// the frame with this index in the page
+    Index(u16),
+    /// the iframe with this name or id
+    Name(String),
+}
+
+impl FromStr for Frame {
+    type Err = std::convert::Infallible;
+
+    /// a number is interpreted as the frame index, everything else as a name
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value.parse() {
+            Ok(index) => Frame::Index(index),
+            Err(_) => Frame::Name(value.to_string()),
+        })
+    }
+}
+
+/// the aspect ratio of the browser window, e.g. 16:10 for a projector
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AspectRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AspectRatio {
+    /// the window size with the given width in this aspect ratio. The
+    /// height is rounded to the nearest pixel
+    pub fn window_size(&self, width: u32) -> (u32, u32) {
+        let height = (width as f64 * self.height as f64 / self.width as f64).round();
+        (width, height as u32)
+    }
+}
+
+impl FromStr for AspectRatio {
+    type Err = String;
+
+    /// parse WIDTH:HEIGHT, e.g. 16:10
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (width, height) = value
+            .split_once(':')
+            .ok_or_else(|| String::from("expected WIDTH:HEIGHT"))?;
+        let parse = |value: &str| match value.trim().parse() {
+            Ok(0) | Err(_) => {
+                Err(format!("invalid aspect ratio component {value:?}"))
+            }
+            Ok(value) => Ok(value),
+        };
+        Ok(AspectRatio { width: parse(width)?, height: parse(height)? })
+    }
+}
+
+/// how errors logged by the browser console are handled. Collecting the
+/// errors requires a Chromium based browser
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConsoleErrorPolicy {
+    /// do not collect console errors
+    Ignore,
+    /// collect console errors and report them with the slide
+    Collect,
+    /// collect console errors and treat slides with errors as violations
+    Fail,
+}
+
+/// how the paths of output files (screenshots and html dumps) are derived
+/// from the paths of the slides
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputLayout {
+    /// mirror the directory layout of the slides
+    Mirror,
+    /// put all files into the output directory, the directories are encoded
+    /// in the file name, e.g. intro__basics.png
+    Flat,
+    /// mirror the layout in a subdirectory with the date of the run, e.g.
+    /// 2024-05-01/intro/basics.png, to keep a history
+    Dated,
+}
+
+impl OutputLayout {
+    /// the output path for the mirrored relative path of a slide. date is
+    /// the date of the run in YYYY-MM-DD format
+    pub(crate) fn output_path(&self, relative_path: &Path, date: &str) -> PathBuf {
+        match self {
+            OutputLayout::Mirror => relative_path.to_path_buf(),
+            OutputLayout::Flat => PathBuf::from(
+                relative_path
+                    .iter()
+                    .map(|component| component.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("__"),
+            ),
+            OutputLayout::Dated => Path::new(date).join(relative_path),
+        }
+    }
+}
+
+/// true if nearly all pixels of the png image have the color of the top left
+/// pixel. Small differences, e.g. from anti-aliasing, are ignored
+fn is_blank(png: &[u8]) -> anyhow::Result<bool> {
+    /// the maximum difference of a color channel to count as the same color
+    const CHANNEL_TOLERANCE: u8 = 16;
+    /// the maximum ratio of pixels with a different color
+    const MAX_DIFFERENT_RATIO: f64 = 0.001;
+    let image = image::load_from_memory(png)?.to_rgba8();
+    let Some(background) = image.pixels().next().copied() else {
+        return Ok(true);
+    };
+    let different_pixels = image
+        .pixels()
+        .filter(|pixel| {
+            pixel.0.iter().zip(background.0).any(|(channel, background)| {
+                channel.abs_diff(background) > CHANNEL_TOLERANCE
+            })
+        })
+        .count();
+    let pixels = image.width() as f64 * image.height() as f64;
+    Ok(different_pixels as f64 <= pixels * MAX_DIFFERENT_RATIO)
+}
+
+/// the current date in UTC in YYYY-MM-DD format
+fn current_date() -> String {
+    let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    timestamp[.."YYYY-MM-DD".len()].to_string()
+}
+
+/// how slides are handled if the evaluated element is not found
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum MissingElementPolicy {
+    /// skip the slide with a warning
+    Skip,
+    /// fail the evaluation
+    Fail,
+    /// record an empty measurement and continue
+    Empty,
+}
+
+/// a network connection emulated by the browser. The presets match the ones
+/// of the Chrome DevTools. Emulating the network requires a Chromium based
+/// browser
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum NetworkThrottle {
+    /// 2000ms latency, 400kbit/s download and upload
+    Slow3g,
+    /// 562.5ms latency, 1.44Mbit/s download and 675kbit/s upload
+    Fast3g,
+}
+
+impl NetworkThrottle {
+    /// the latency in milliseconds and the download and upload throughput in
+    /// bytes per second
+    fn conditions(&self) -> (f64, f64, f64) {
+        match self {
+            NetworkThrottle::Slow3g => (2000.0, 50000.0, 50000.0),
+            NetworkThrottle::Fast3g => (562.5, 180000.0, 84375.0),
+        }
+    }
+}
+
+/// the amount in pixels an element exceeds the maximum size of a slide.
+/// zero if the element fits in that direction
+#[derive(Debug)]
+struct Overflow {
+    /// overflow beyond the maximum width
+    horizontal: f64,
+    /// overflow beyond the maximum height
+    vertical: f64,
+}
+
+/// the number of slides with a height in [min_height, max_height)
+#[derive(Debug, Serialize)]
+pub struct HistogramBucket {
+    /// the lower bound (inclusive) of the bucket
+    pub min_height: usize,
+    /// the upper bound (exclusive) of the bucket
+    pub max_height: usize,
+    /// the number of slides in this bucket
+    pub count: usize,
+}
+
+/// the measured slides of a chapter, the first directory (or file) below
+/// the source directory
+#[derive(Debug, Serialize)]
+pub struct ChapterSummary {
+    /// the first path segment of the slides below the source directory
+    pub chapter: String,
+    /// the number of measured slides in the chapter
+    pub count: usize,
+    /// the number of failing slides in the chapter
+    pub failures: usize,
+    /// the slide with the highest fill ratio
+    pub worst_slide: String,
+    /// the fill percentage of the worst slide
+    pub worst_fill_percent: f64,
+}
+
+/// the outcome of a run for the status file. The counts are zero if the
+/// run failed before the slides were evaluated
+#[derive(Debug, Default, Serialize)]
+pub struct RunStatus {
+    /// the number of measured slides that do not fail
+    pub passed: usize,
+    /// the number of measured slides that fail
+    pub failed: usize,
+    /// the number of slides that were not measured
+    pub skipped: usize,
+    /// true if the run succeeded
+    pub ok: bool,
+    /// the exit code of the run
+    pub exit_code: u8,
+}
+
+impl RunStatus {
+    /// write the status as a json object to the file, e.g. for CI systems
+    /// that cannot read the exit code. An existing file is replaced
+    pub fn export(&self, file: &Path) -> anyhow::Result<()> {
+        fs::write(file, serde_json::to_vec(self)?)
+            .with_context(|| format!("cannot write {}", file.display()))?;
+        Ok(())
+    }
+}
+
+/// the reason why a slide was not measured
+#[derive(Debug, Display, Serialize)]
+pub enum SkipReason {
+    /// the content element was not found on the slide
+    NoContent,
+    /// the browser could not open or load the slide
+    NavigationFailed,
+}
+
+impl SkipReason {
+    /// a short description of the reason for summaries
+    fn description(&self) -> &'static str {
+        match self {
+            SkipReason::NoContent => "no content",
+            SkipReason::NavigationFailed => "navigation failed",
+        }
+    }
+}
+
+/// a slide that was not measured
+#[derive(Debug)]
+pub struct SkippedSlide {
+    /// metadata about the slide
+    pub slide: Slide,
+    /// why the slide was skipped
+    pub reason: SkipReason,
+    /// the error that caused the skip if any
+    pub error: Option<String>,
+}
+
+/// the context of errors while navigating to a slide. The evaluation skips
+/// such slides instead of failing entirely
+#[derive(Debug)]
+struct NavigationFailure;
+
+impl fmt::Display for NavigationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot navigate to the slide")
+    }
 }
 
 /// holds all evaluation results for a book
 pub struct EvaluationResults {
     /// metadata about the book
-    _book: Book,
+    book: Book,
     /// the collected evaluation results
     results: Vec<EvaluationResult>,
+    /// the slides that were not measured
+    skipped: Vec<SkippedSlide>,
+    /// a label of the run, e.g. a commit SHA or CI build number
+    label: Option<String>,
+    /// the delay between opening and measuring each slide if applied
+    settle_delay: Option<Duration>,
+    /// true if the slides were measured with reduced motion
+    reduced_motion: bool,
+    /// the cpu slowdown factor the slides were measured with if applied
+    cpu_throttle: Option<f64>,
+    /// the network connection the slides were measured with if applied
+    network_throttle: Option<NetworkThrottle>,
+    /// count slides with warnings as failures
+    warnings_as_errors: bool,
+    /// the unit of the exported dimensions
+    unit: Unit,
+    /// the slide policy the slides were evaluated with
+    slide_policy: SlidePolicy,
+    /// the maximum height derived from the heights of the slides if applied
+    height_threshold: Option<HeightThreshold>,
+}
+
+/// a maximum height derived from the heights of the evaluated slides
+#[derive(Debug, Clone, Copy)]
+struct HeightThreshold {
+    /// the percentile (0 to 100) of the slide heights
+    percentile: f64,
+    /// the height at the percentile
+    max_height: usize,
+}
+
+/// the order of the exported results
+#[derive(Debug, Clone, Copy, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResultOrder {
+    /// the order in which the slides were evaluated
+    Slide,
+    /// the largest overflow first
+    Overflow,
+    /// the highest fill ratio first
+    FillRatio,
+}
+
+/// the unit of the exported dimensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    /// css pixels as measured by the browser
+    Px,
+    /// multiples of the font size of the root element, e.g. for themes that
+    /// are laid out in rem
+    Rem,
+}
+
+/// how measured dimensions are rounded when they are exported. Only the
+/// exported values are rounded, the verdict does not depend on the rounding
+/// mode: the maximum width and height are compared with the measured size
+/// truncated to whole pixels and the fold with the measured bottom of the
+/// element. An element of 750.6 pixels fits a maximum width of 750 pixels
+/// although its width is exported as 751 when it is rounded
+#[derive(Debug, Clone, Copy, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundingMode {
+    /// round to the nearest integer
+    Round,
+    /// round down, the exported width and height are the ones compared with
+    /// the maximum size
+    Floor,
+    /// round up
+    Ceil,
+    /// export the value as measured by the browser
+    None,
+}
+
+impl RoundingMode {
+    /// apply the rounding mode to a dimension
+    fn apply(&self, value: f64) -> f64 {
+        match self {
+            RoundingMode::Round => value.round(),
+            RoundingMode::Floor => value.floor(),
+            RoundingMode::Ceil => value.ceil(),
+            RoundingMode::None => value,
+        }
+    }
+}
+
+/// serialize integral dimensions without a fractional part
+fn serialize_dimension<S: serde::Serializer>(
+    value: &f64,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if value.fract() == 0.0 {
+        serializer.serialize_u64(*value as u64)
+    } else {
+        serializer.serialize_f64(*value)
+    }
+}
+
+/// serialize an optional dimension, see serialize_dimension
+fn serialize_optional_dimension<S: serde::Serializer>(
+    value: &Option<f64>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serialize_dimension(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// serialize a duration in a human readable form, e.g. 1s 500ms
+fn serialize_optional_duration<S: serde::Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => {
+            serializer.collect_str(&humantime::format_duration(*duration))
+        }
+        None => serializer.serialize_none(),
+    }
 }
 
 #[derive(Serialize)]
 struct ExportFormat {
+    label: Option<String>,
+    settle_delay_ms: Option<u128>,
+    reduced_motion: bool,
+    cpu_throttle: Option<f64>,
+    network_throttle: Option<NetworkThrottle>,
+    source_dir: PathBuf,
     filename: PathBuf,
-    element_width: usize,
-    element_height: usize,
+    fragment: Option<String>,
+    slide_id: Option<String>,
+    #[serde(serialize_with = "serialize_optional_dimension")]
+    element_x: Option<f64>,
+    #[serde(serialize_with = "serialize_optional_dimension")]
+    element_y: Option<f64>,
+    #[serde(serialize_with = "serialize_dimension")]
+    element_width: f64,
+    #[serde(serialize_with = "serialize_dimension")]
+    element_height: f64,
+    #[serde(serialize_with = "serialize_dimension")]
+    page_scroll_height: f64,
+    device_pixel_ratio: f64,
+    root_font_size: f64,
+    unit: Unit,
+    render_time_ms: Option<u128>,
+    max_code_lines: usize,
+    clipped_elements: usize,
+    fragment_steps: usize,
+    max_height_override: Option<usize>,
+    #[serde(serialize_with = "serialize_optional_dimension")]
+    content_width: Option<f64>,
+    #[serde(serialize_with = "serialize_optional_dimension")]
+    content_height: Option<f64>,
+    metrics: String,
+    #[serde(serialize_with = "serialize_dimension")]
+    horizontal_overflow: f64,
+    #[serde(serialize_with = "serialize_dimension")]
+    vertical_overflow: f64,
+    screenshot: Option<PathBuf>,
+    screenshot_clipped: bool,
+    console_errors: String,
     policy_violations: String,
+    #[serde(serialize_with = "serialize_dimension")]
+    fill_percent: f64,
+    severity: Severity,
+    failure_message: Option<String>,
+    unstable: bool,
+    blank: bool,
+    extra_css_applied: Option<bool>,
+    retries: usize,
+    estimated: bool,
 }
 
 impl EvaluationResults {
-    /// export the evaluation results to the given csv file, overwrites if
-    /// allowed
-    pub fn export_csv(
-        &self,
-        file: &Path,
-        overwrite: bool,
-        violations_only: bool,
-    ) -> anyhow::Result<()> {
-        if file.exists() && !overwrite {
-            Err(anyhow!(
-                "Not allowed to overwrite existing evaluation results at {}",
-                file.display()
-            ))?;
-        };
+    /// annotate the results with a label of the run, e.g. a commit SHA or CI
+    /// build number. It is added to every exported record
+    pub fn with_label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
 
-        let mut csv_writer = csv::Writer::from_path(file)?;
-        for result in &self.results {
-            if violations_only && result.policy_violations.is_empty() {
-                continue;
+    /// sort the results in the given order, e.g. to list the worst slides
+    /// first. The sort is stable, slides that compare equal keep their order
+    pub fn sorted(mut self, order: ResultOrder) -> Self {
+        match order {
+            ResultOrder::Slide => {}
+            ResultOrder::Overflow => self.results.sort_by(|a, b| {
+                let overflow = |result: &EvaluationResult| {
+                    result.overflow.horizontal + result.overflow.vertical
+                };
+                overflow(b).total_cmp(&overflow(a))
+            }),
+            ResultOrder::FillRatio => {
+                self.results.sort_by(|a, b| b.fill_ratio.total_cmp(&a.fill_ratio))
             }
-            csv_writer.serialize(ExportFormat {
-                filename: (*result.slide.filename).to_path_buf(),
-                element_width: result.element_size.width.round() as usize,
-                element_height: result.element_size.height.round() as usize,
-                policy_violations: result
-                    .policy_violations
-                    .iter()
-                    .map(PolicyViolation::to_string)
-                    .collect::<Vec<_>>()
-                    .join(";"),
-            })?;
         }
-        Ok(())
+        self
     }
 
-    /// dump the results to stdout
-    pub fn export_stdout(&self, violations_only: bool) {
-        for result in &self.results {
-            if violations_only && result.policy_violations.is_empty() {
-                continue;
-            }
-            println!(
-                "{}: {}x{} [{}]",
-                result.slide.filename.display(),
-                result.element_size.width,
-                result.element_size.height,
-                result
-                    .policy_violations
-                    .iter()
-                    .map(PolicyViolation::to_string)
-                    .collect::<Vec<_>>()
-                    .join(";"),
-            );
-        }
+    /// count slides with warnings as failures, e.g. to tighten the slide
+    /// policy over time
+    pub fn with_warnings_as_errors(mut self, warnings_as_errors: bool) -> Self {
+        self.warnings_as_errors = warnings_as_errors;
+        self
     }
-}
 
-impl<'a> Evaluator<'_> {
-    /// create a new instance with the provided config.
-    /// fails if the webclient cannot be created
-    pub fn new(
-        webclient: Client,
-        element_selector: &'a str,
-        screenshot_dir: Option<PathBuf>,
-        html_base_url: Url,
-        source_dir: PathBuf,
-        cancellation_token: CancellationToken,
-        slide_policy: SlidePolicy,
-    ) -> Evaluator<'a> {
-        let element_selector = fantoccini::Locator::XPath(element_selector);
-        Evaluator {
-            webclient,
-            element_selector,
-            screenshot_dir,
-            html_base_url,
-            source_dir,
-            cancellation_token,
-            slide_policy,
-        }
+    /// export the dimensions in this unit. Rem values are only meaningful
+    /// without rounding or with a fine rounding
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
     }
 
-    /// navigate the webdriver to the given url.
-    /// ensure that html_base_url is set before calling this
-    /// after this call the webdriver will see the content at the url
-    async fn webdriver_open_url(&self, url: &Url) -> Result<(), anyhow::Error> {
-        debug!("open url in webclient: {}", url);
-        self.webclient.goto(url.as_str()).await?;
-        Ok(())
+    /// the element height below which the given percentage (0 to 100) of the
+    /// measured slides are, using the nearest rank. None if no slide was
+    /// measured
+    pub fn height_percentile(&self, percentile: f64) -> Option<f64> {
+        let mut heights: Vec<f64> =
+            self.results.iter().map(|result| result.element_size.height).collect();
+        heights.sort_by(f64::total_cmp);
+        let rank = (percentile / 100.0 * heights.len() as f64).ceil() as usize;
+        heights.get(rank.clamp(1, heights.len().max(1)) - 1).copied()
     }
 
-    /// evaluate the currently opened webpage return the selected content
-    /// element if available
-    async fn get_content_element_from_slide(
-        &self,
-    ) -> anyhow::Result<Option<Element>> {
-        match self.webclient.find(self.element_selector).await {
-            Result::Ok(result) => Ok(Some(result)),
-            Result::Err(fantoccini::error::CmdError::Standard(
-                fantoccini::error::WebDriver {
-                    error: fantoccini::error::ErrorStatus::NoSuchElement,
-                    ..
-                },
-            )) => anyhow::Ok(None),
-            Result::Err(error) => Err(anyhow!(error))?,
+    /// replace the maximum height of the slide policy with the height at the
+    /// given percentile of the measured slides, e.g. 95 to flag the highest
+    /// slides of the book as outliers without an absolute threshold. The size
+    /// violations, overflow, fill ratio, severity and failure message of each
+    /// slide are evaluated again with the derived maximum height. Slides
+    /// above it violate HeightOutlier instead of MaxHeight. A maximum height
+    /// mapped to a class of the element still applies to its slide
+    pub fn with_height_threshold_from_baseline(mut self, percentile: f64) -> Self {
+        let Some(threshold) = self.height_percentile(percentile) else {
+            return self;
+        };
+        info!("height threshold at the {percentile}th percentile: {threshold}");
+        let height_threshold =
+            HeightThreshold { percentile, max_height: threshold as usize };
+        for result in &mut self.results {
+            let size = &result.element_size;
+            let outlier_violation = match result.max_height_override {
+                Some(_) => PolicyViolation::MaxHeight,
+                None => PolicyViolation::HeightOutlier,
+            };
+            let slide_policy = self.slide_policy.with_max_height(Some(
+                result.max_height_override.unwrap_or(height_threshold.max_height),
+            ));
+            // replace the size violations of a previous evaluation
+            result.policy_violations.retain(|violation| !violation.is_size());
+            let size_violations =
+                slide_policy.eval_size(size).into_iter().map(|violation| {
+                    match violation {
+                        PolicyViolation::MaxHeight => outlier_violation,
+                        violation => violation,
+                    }
+                });
+            result.policy_violations.splice(0..0, size_violations);
+            result.overflow = slide_policy.eval_overflow(size);
+            result.fill_ratio = slide_policy.eval_fill_ratio(size);
+            result.severity = slide_policy
+                .eval_severity(result.fill_ratio, &result.policy_violations);
         }
+        self.height_threshold = Some(height_threshold);
+        self
     }
 
-    /// extract the element coordinates from this element
-    async fn get_element_coordinates(
-        &self,
-        element: &Element,
-    ) -> anyhow::Result<ElementSize> {
-        let coordinates = Into::<ElementSize>::into(element.rectangle().await?);
-        Ok(coordinates)
+    /// the slide policy that was applied to the result: the maximum height
+    /// mapped to a class of its element replaces the maximum height,
+    /// otherwise the derived height threshold does if applied
+    fn slide_policy_of(&self, result: &EvaluationResult) -> Cow<'_, SlidePolicy> {
+        self.slide_policy.with_max_height(
+            result.max_height_override.or(self
+                .height_threshold
+                .map(|height_threshold| height_threshold.max_height)),
+        )
     }
 
-    /// store the screenshot as png to the given path
-    fn store_screenshot(
+    /// the message of the failure message template of the slide policy of
+    /// the result if the slide fails. The dimensions are exported like the
+    /// dimension columns
+    fn failure_message(
         &self,
-        screenshot: Vec<u8>,
-        filename: &Path,
-    ) -> anyhow::Result<()> {
-        let relative_filename = filename.strip_prefix(&self.source_dir)?;
-        let output_filename = self
-            .screenshot_dir
-            .as_ref()
-            .unwrap()
-            .join(relative_filename.with_extension("png"));
-        debug!("write screenshot to {}", output_filename.to_str().unwrap());
+        result: &EvaluationResult,
+        rounding: RoundingMode,
+    ) -> Option<String> {
+        self.slide_policy_of(result).eval_failure_message(
+            &result.slide,
+            &result.element_size,
+            result.severity,
+            |value| rounding.apply(self.dimension(result, value)),
+        )
+    }
 
-        // create directories if necessary
-        let output_dir = output_filename.parent().unwrap();
-        if !output_dir.exists() {
-            debug!("creating {}", output_dir.to_str().unwrap());
-            fs::create_dir_all(output_dir)?;
+    /// the exported value of a dimension of the result. The browser reports
+    /// the dimensions in css pixels, independent of the device pixel ratio
+    fn dimension(&self, result: &EvaluationResult, mut value: f64) -> f64 {
+        if self.unit == Unit::Rem && result.root_font_size > 0.0 {
+            value /= result.root_font_size;
         }
+        value
+    }
 
-        let mut file =
-            fs::OpenOptions::new().create(true).write(true).open(output_filename)?;
+    /// estimate the size of each slide of the book from its html without a
+    /// browser, see [crate::estimate]. The results are marked as estimated
+    /// and only a rough approximation of the measured sizes. The element is
+    /// found with the element selector of the slide or the given one, only
+    /// the first max_slides slides are estimated. Slides whose element is not
+    /// found are skipped
+    pub fn estimate(
+        book: Book,
+        element_selector: &str,
+        max_slides: Option<usize>,
+        slide_policy: &SlidePolicy,
+        metrics: &LayoutMetrics,
+    ) -> anyhow::Result<EvaluationResults> {
+        let default_element: ElementPath = element_selector.parse()?;
+        let mut results = vec![];
+        let mut skipped = vec![];
+        let max_slides = max_slides.unwrap_or(usize::MAX);
+        for slide in book.slides().iter().take(max_slides) {
+            let (element_selector, element) = match &slide.element_selector {
+                Some(selector) => (selector.clone(), selector.parse()?),
+                None => (element_selector.into(), default_element.clone()),
+            };
+            let html = slide.read_html_text()?;
+            let Some(estimate) =
+                estimate_layout(&html, &element, slide.fragment.as_deref(), metrics)
+            else {
+                warn!("slide with no content - ignore: {:?}", slide);
+                skipped.push(SkippedSlide {
+                    slide: slide.clone(),
+                    reason: SkipReason::NoContent,
+                    error: None,
+                });
+                continue;
+            };
+            let element_size = estimate.element_size;
+            let policy_violations = slide_policy.eval_size(&element_size);
+            let fill_ratio = slide_policy.eval_fill_ratio(&element_size);
+            let severity =
+                slide_policy.eval_severity(fill_ratio, &policy_violations);
+            results.push(EvaluationResult {
+                slide: slide.clone(),
+                overflow: slide_policy.eval_overflow(&element_size),
+                element_size,
+                page_scroll_height: 0.0,
+                device_pixel_ratio: 1.0,
+                root_font_size: metrics.font_size,
+                render_time: None,
+                max_code_lines: estimate.max_code_lines,
+                clipped_elements: 0,
+                fragment_steps: 0,
+                max_height_override: None,
+                content_box: None,
+                metrics: vec![],
+                screenshot: None,
+                screenshot_clipped: false,
+                console_errors: vec![],
+                policy_violations,
+                fill_ratio,
+                severity,
+                unstable: false,
+                blank: false,
+                extra_css_applied: None,
+                retries: 0,
+                estimated: true,
+                element_selector: Some(element_selector),
+            });
+        }
+        Ok(EvaluationResults {
+            book,
+            results,
+            skipped,
+            label: None,
+            settle_delay: None,
+            reduced_motion: false,
+            cpu_throttle: None,
+            network_throttle: None,
+            warnings_as_errors: false,
+            unit: Unit::Px,
+            slide_policy: slide_policy.clone(),
+            height_threshold: None,
+        })
+    }
 
-        file.write_all(&screenshot)?;
-        Ok(())
+    /// merge the results of another (partial) run into these results, e.g.
+    /// of a book that is evaluated in shards. A result of the other run
+    /// replaces the result of the same slide. Slides are identified like in
+    /// [merge_csv_exports], so runs of different checkouts merge
+    pub fn merge(mut self, other: EvaluationResults) -> Self {
+        let slide_key = |slide: &Slide| {
+            let relative_filename = slide
+                .filename
+                .strip_prefix(&slide.source_dir)
+                .unwrap_or(&slide.filename)
+                .to_path_buf();
+            (relative_filename, slide.fragment.clone())
+        };
+        let other_slides: HashSet<_> = other
+            .results
+            .iter()
+            .map(|result| slide_key(&result.slide))
+            .chain(other.skipped.iter().map(|skipped| slide_key(&skipped.slide)))
+            .collect();
+        self.results
+            .retain(|result| !other_slides.contains(&slide_key(&result.slide)));
+        self.skipped
+            .retain(|skipped| !other_slides.contains(&slide_key(&skipped.slide)));
+        self.results.extend(other.results);
+        self.skipped.extend(other.skipped);
+        self
     }
 
-    /// evaluate a single slide
-    pub async fn eval_slide(
-        &self,
-        slide: &Slide,
-    ) -> anyhow::Result<Option<EvaluationResult>> {
-        debug!("evaluating {:?}", slide);
+    /// return the slides that were skipped during the evaluation
+    pub fn skipped(&self) -> &[SkippedSlide] {
+        &self.skipped
+    }
 
-        let url = self.html_base_url.join(&slide.filename.display().to_string())?;
-        self.webdriver_open_url(&url).await?;
+    /// return the pages (relative to a source directory) that are not among
+    /// the measured slides, e.g. chapters of the SUMMARY.md that failed to
+    /// build or were skipped
+    pub fn missing_pages<'p>(&self, pages: &'p [PathBuf]) -> Vec<&'p Path> {
+        let measured_pages: HashSet<PathBuf> = self
+            .results
+            .iter()
+            .filter_map(|result| {
+                let html_filename = result.slide.html_filename();
+                let relative_filename =
+                    html_filename.strip_prefix(&result.slide.source_dir).ok()?;
+                Some(relative_filename.to_path_buf())
+            })
+            .collect();
+        pages
+            .iter()
+            .map(PathBuf::as_path)
+            .filter(|page| !measured_pages.contains(*page))
+            .collect()
+    }
 
-        let Some(content_element) = self.get_content_element_from_slide().await?
-        else {
-            return Ok(None);
-        };
-        let element_size = self.get_element_coordinates(&content_element).await?;
-        if self.screenshot_dir.is_some() {
-            let screenshot = content_element.screenshot().await?;
-            self.store_screenshot(screenshot, &slide.filename)?;
+    /// the most common element size (width, height) and the number of slides
+    /// with exactly this size if at least min_slides slides were measured and
+    /// at least the given fraction of them share the size. Empty elements are
+    /// ignored. Many slides of the same size usually mean that the XPath
+    /// matches a fixed size container, e.g. the navigation, instead of the
+    /// content
+    pub fn identical_size(
+        &self,
+        min_slides: usize,
+        min_fraction: f64,
+    ) -> Option<((f64, f64), usize)> {
+        let sizes: Vec<(f64, f64)> = self
+            .results
+            .iter()
+            .map(|result| (result.element_size.width, result.element_size.height))
+            .filter(|(width, height)| width * height > 0.0)
+            .collect();
+        if sizes.len() < min_slides {
+            return None;
         }
-        let policy_violations = self.slide_policy.eval_size(&element_size);
-        let result = EvaluationResult {
-            slide: slide.clone(),
-            element_size,
-            policy_violations,
-        };
-        debug!("information about element: {:?}", result);
-        Ok(Some(result))
+        let mut counts = HashMap::new();
+        for (width, height) in &sizes {
+            *counts.entry((width.to_bits(), height.to_bits())).or_insert(0) += 1;
+        }
+        // the first of equally common sizes
+        let (size, count) = sizes
+            .iter()
+            .map(|(width, height)| {
+                ((*width, *height), counts[&(width.to_bits(), height.to_bits())])
+            })
+            .reduce(
+                |most_common, size| {
+                    if size.1 > most_common.1 {
+                        size
+                    } else {
+                        most_common
+                    }
+                },
+            )?;
+        (count as f64 >= min_fraction * sizes.len() as f64).then_some((size, count))
     }
 
-    /// evaluate an entire book
-    pub async fn eval_book(&self, book: Book) -> anyhow::Result<EvaluationResults> {
-        let mut results = vec![];
-        debug!("slide count: {}", book.slides().len());
-        for slide in book.slides().iter() {
-            if self.cancellation_token.is_cancelled() {
-                debug!("received cancel request, return already completed results");
-                break;
+    /// the number of slides whose element was matched by each XPath
+    pub fn selector_counts(&self) -> BTreeMap<Arc<str>, usize> {
+        let mut counts = BTreeMap::new();
+        for result in &self.results {
+            if let Some(element_selector) = &result.element_selector {
+                *counts.entry(element_selector.clone()).or_insert(0) += 1;
             }
-            let Some(result) = self.eval_slide(slide).await? else {
-                warn!("slide with no content - ignore: {:?}", slide);
-                continue;
-            };
-            results.push(result);
         }
-        Ok(EvaluationResults { _book: book, results })
+        counts
     }
-}
-
-/// all possible policy violations
-#[derive(Debug, Display, Serialize)]
-enum PolicyViolation {
-    /// violation of the maximum height
-    MaxWidth,
-    /// violation of the maximum width
-    MaxHeight,
-}
 
-/// the SlidePolicy struct contains all parameters for evaluating a slide
-pub struct SlidePolicy {
-    /// the maximum allowed width of a slide
-    pub max_width: usize,
-    /// the maximum allowed height of a slide
-    pub max_height: usize,
-}
+    /// the number of measured slides with the given severity
+    pub fn severity_count(&self, severity: Severity) -> usize {
+        self.results.iter().filter(|result| result.severity == severity).count()
+    }
 
-impl SlidePolicy {
-    /// evaluate if the width is within the policy
-    fn eval_width(&self, element_size: &ElementSize) -> Option<PolicyViolation> {
-        if element_size.width as usize > self.max_width {
-            return Some(PolicyViolation::MaxWidth);
+    /// true if the result violates the slide policy. Slides with warnings
+    /// are included if warnings are treated as errors
+    fn is_failure(&self, result: &EvaluationResult) -> bool {
+        match result.severity {
+            Severity::Fail => true,
+            Severity::Warn => self.warnings_as_errors,
+            Severity::Pass => false,
         }
-        return None;
     }
 
-    /// evaluate if the width is within the policy
-    fn eval_height(&self, element_size: &ElementSize) -> Option<PolicyViolation> {
-        if element_size.height as usize > self.max_height {
-            return Some(PolicyViolation::MaxHeight);
+    /// the number of measured slides that violate the slide policy. Slides
+    /// with warnings are included if warnings are treated as errors
+    pub fn failure_count(&self) -> usize {
+        self.results.iter().filter(|result| self.is_failure(result)).count()
+    }
+
+    /// the results that are exported. If violations_only is set, only the
+    /// failing slides and slides with policy violations are exported. The
+    /// filter is the same for all export formats
+    fn exported_results(
+        &self,
+        violations_only: bool,
+    ) -> impl Iterator<Item = &EvaluationResult> {
+        self.results.iter().filter(move |result| {
+            !violations_only
+                || !result.policy_violations.is_empty()
+                || self.is_failure(result)
+        })
+    }
+
+    /// true if any measured slide violates the slide policy. Warnings are
+    /// only failures if warnings are treated as errors
+    pub fn has_failures(&self) -> bool {
+        self.failure_count() > 0
+    }
+
+    /// the percentage of measured slides that violate the slide policy
+    pub fn failure_percentage(&self) -> f64 {
+        if self.results.is_empty() {
+            return 0.0;
         }
-        return None;
+        self.failure_count() as f64 * 100.0 / self.results.len() as f64
     }
 
-    /// evaluate all size policies
-    fn eval_size(&self, element_size: &ElementSize) -> Vec<PolicyViolation> {
-        [self.eval_height(element_size), self.eval_width(element_size)]
+    /// export the evaluation results to the given csv file, overwrites if
+    /// allowed. Dimensions are rounded with the given rounding mode. The
+    /// position of the element is only filled in if verbose is set
+    pub fn export_csv(
+        &self,
+        file: &Path,
+        overwrite: bool,
+        violations_only: bool,
+        rounding: RoundingMode,
+        verbose: bool,
+    ) -> anyhow::Result<()> {
+        if file.exists() && !overwrite {
+            Err(anyhow!(
+                "Not allowed to overwrite existing evaluation results at {}",
+                file.display()
+            ))?;
+        };
+
+        let mut csv_writer = csv::Writer::from_path(file)
+            .with_context(|| format!("cannot create {}", file.display()))?;
+        for result in self.exported_results(violations_only) {
+            csv_writer.serialize(self.export_record(result, rounding, verbose))?;
+        }
+        Ok(())
+    }
+
+    /// export the results as an SQL script for SQLite, e.g. to collect the
+    /// slide sizes of many commits in a database with `sqlite3 history.db <
+    /// run.sql`. The script creates the tables runs and slide_results if
+    /// they do not exist and inserts the run with the given id and a row per
+    /// measured slide in a single transaction. A slide of a run is identified
+    /// by its file name relative to its source directory and its fragment.
+    /// Rows that exist are kept, so importing a script again changes nothing
+    /// and the shards of a run with the same id add their slides to a single
+    /// run. Sizes are in pixels as measured. Overwrites the file if allowed
+    pub fn export_sql(
+        &self,
+        file: &Path,
+        overwrite: bool,
+        run_id: &str,
+        timestamp: SystemTime,
+    ) -> anyhow::Result<()> {
+        if file.exists() && !overwrite {
+            Err(anyhow!(
+                "Not allowed to overwrite existing evaluation results at {}",
+                file.display()
+            ))?;
+        };
+        let mut sql = String::from(SQL_SCHEMA);
+        sql.push_str("BEGIN;\n");
+        sql.push_str(&format!(
+            "INSERT OR IGNORE INTO runs VALUES ({}, {}, {}, {}, {});\n",
+            sql_text(run_id),
+            self.label.as_deref().map_or(String::from("NULL"), sql_text),
+            sql_text(&humantime::format_rfc3339_seconds(timestamp).to_string()),
+            self.results.len(),
+            self.failure_count()
+        ));
+        for result in &self.results {
+            let filename = result
+                .slide
+                .filename
+                .strip_prefix(&result.slide.source_dir)
+                .unwrap_or(&result.slide.filename);
+            sql.push_str(&format!(
+                "INSERT OR IGNORE INTO slide_results VALUES \
+                 ({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {});\n",
+                sql_text(run_id),
+                result.slide.id().as_deref().map_or(String::from("NULL"), sql_text),
+                sql_text(&filename.display().to_string()),
+                result
+                    .slide
+                    .fragment
+                    .as_deref()
+                    .map_or(String::from("NULL"), sql_text),
+                result.element_size.width,
+                result.element_size.height,
+                result.page_scroll_height,
+                result.max_code_lines,
+                result.render_time.map_or(String::from("NULL"), |time| time
+                    .as_millis()
+                    .to_string()),
+                sql_text(&result.severity.to_string()),
+                sql_text(
+                    &result
+                        .policy_violations
+                        .iter()
+                        .map(PolicyViolation::to_string)
+                        .collect::<Vec<_>>()
+                        .join(";")
+                ),
+            ));
+        }
+        sql.push_str("COMMIT;\n");
+        fs::write(file, sql)
+            .with_context(|| format!("cannot write {}", file.display()))?;
+        Ok(())
+    }
+
+    /// write the results as newline delimited json (JSON Lines) with one
+    /// object per slide. Each record is written as soon as it is serialized
+    pub fn export_jsonl<W: io::Write>(
+        &self,
+        mut writer: W,
+        violations_only: bool,
+        rounding: RoundingMode,
+        verbose: bool,
+    ) -> anyhow::Result<()> {
+        for result in self.exported_results(violations_only) {
+            serde_json::to_writer(
+                &mut writer,
+                &self.export_record(result, rounding, verbose),
+            )?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// the exported record of a result. The position of the element is only
+    /// filled in if verbose is set
+    fn export_record(
+        &self,
+        result: &EvaluationResult,
+        rounding: RoundingMode,
+        verbose: bool,
+    ) -> ExportFormat {
+        ExportFormat {
+            label: self.label.clone(),
+            settle_delay_ms: self.settle_delay.map(|delay| delay.as_millis()),
+            reduced_motion: self.reduced_motion,
+            cpu_throttle: self.cpu_throttle,
+            network_throttle: self.network_throttle,
+            source_dir: (*result.slide.source_dir).to_path_buf(),
+            filename: (*result.slide.filename).to_path_buf(),
+            fragment: result.slide.fragment.clone(),
+            slide_id: result.slide.id(),
+            element_x: verbose.then(|| {
+                rounding.apply(self.dimension(result, result.element_size.x))
+            }),
+            element_y: verbose.then(|| {
+                rounding.apply(self.dimension(result, result.element_size.y))
+            }),
+            element_width: rounding
+                .apply(self.dimension(result, result.element_size.width)),
+            element_height: rounding
+                .apply(self.dimension(result, result.element_size.height)),
+            page_scroll_height: rounding
+                .apply(self.dimension(result, result.page_scroll_height)),
+            device_pixel_ratio: result.device_pixel_ratio,
+            root_font_size: result.root_font_size,
+            unit: self.unit,
+            render_time_ms: result.render_time.map(|time| time.as_millis()),
+            max_code_lines: result.max_code_lines,
+            clipped_elements: result.clipped_elements,
+            fragment_steps: result.fragment_steps,
+            max_height_override: result.max_height_override,
+            content_width: result
+                .content_box
+                .map(|(width, _)| rounding.apply(self.dimension(result, width))),
+            content_height: result
+                .content_box
+                .map(|(_, height)| rounding.apply(self.dimension(result, height))),
+            metrics: result
+                .metrics
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join(";"),
+            horizontal_overflow: rounding
+                .apply(self.dimension(result, result.overflow.horizontal)),
+            vertical_overflow: rounding
+                .apply(self.dimension(result, result.overflow.vertical)),
+            screenshot: result.screenshot.clone(),
+            screenshot_clipped: result.screenshot_clipped,
+            console_errors: result.console_errors.join(";"),
+            policy_violations: result
+                .policy_violations
+                .iter()
+                .map(PolicyViolation::to_string)
+                .collect::<Vec<_>>()
+                .join(";"),
+            fill_percent: rounding.apply(result.fill_ratio * 100.0),
+            severity: result.severity,
+            failure_message: self.failure_message(result, rounding),
+            unstable: result.unstable,
+            blank: result.blank,
+            extra_css_applied: result.extra_css_applied,
+            retries: result.retries,
+            estimated: result.estimated,
+        }
+    }
+
+    /// bucket the slides by the height of their content element. Each bucket
+    /// covers bucket_size pixels and holds the number of slides in it.
+    /// Buckets between the lowest and highest height are included even if
+    /// they are empty
+    pub fn height_histogram(&self, bucket_size: usize) -> Vec<HistogramBucket> {
+        let bucket_size = bucket_size.max(1);
+        let mut counts = BTreeMap::new();
+        for result in &self.results {
+            let bucket = result.element_size.height as usize / bucket_size;
+            *counts.entry(bucket).or_insert(0) += 1;
+        }
+        let (Some(first), Some(last)) =
+            (counts.keys().next().copied(), counts.keys().last().copied())
+        else {
+            return vec![];
+        };
+        (first..=last)
+            .map(|bucket| HistogramBucket {
+                min_height: bucket * bucket_size,
+                max_height: (bucket + 1) * bucket_size,
+                count: counts.get(&bucket).copied().unwrap_or(0),
+            })
+            .collect()
+    }
+
+    /// group the results by chapter, the first path segment of the slides
+    /// below their source directory, e.g. to assign overflowing slides to
+    /// the owners of a chapter. Chapters are sorted by name
+    pub fn chapter_summaries(&self) -> Vec<ChapterSummary> {
+        let mut chapters: BTreeMap<String, Vec<&EvaluationResult>> = BTreeMap::new();
+        for result in &self.results {
+            let html_filename = result.slide.html_filename();
+            let relative_filename = html_filename
+                .strip_prefix(&result.slide.source_dir)
+                .unwrap_or(&html_filename);
+            let chapter = relative_filename
+                .components()
+                .next()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .unwrap_or_default();
+            chapters.entry(chapter).or_default().push(result);
+        }
+        chapters
             .into_iter()
-            .flatten()
+            .map(|(chapter, results)| {
+                let worst = results
+                    .iter()
+                    .max_by(|a, b| a.fill_ratio.total_cmp(&b.fill_ratio))
+                    .expect("chapters have at least one slide");
+                ChapterSummary {
+                    chapter,
+                    count: results.len(),
+                    failures: results
+                        .iter()
+                        .filter(|result| self.is_failure(result))
+                        .count(),
+                    worst_slide: worst.slide.to_string(),
+                    worst_fill_percent: (worst.fill_ratio * 100.0).round(),
+                }
+            })
             .collect()
     }
+
+    /// export the chapter summaries to the given csv file, overwrites if
+    /// allowed
+    pub fn export_chapters_csv(
+        &self,
+        file: &Path,
+        overwrite: bool,
+    ) -> anyhow::Result<()> {
+        if file.exists() && !overwrite {
+            Err(anyhow!(
+                "Not allowed to overwrite existing chapter summary at {}",
+                file.display()
+            ))?;
+        };
+
+        let mut csv_writer = csv::Writer::from_path(file)
+            .with_context(|| format!("cannot create {}", file.display()))?;
+        for chapter in self.chapter_summaries() {
+            csv_writer.serialize(chapter)?;
+        }
+        Ok(())
+    }
+
+    /// the number of passed, failed and skipped slides for the status file.
+    /// The outcome of the run is set by the caller
+    pub fn run_status(&self) -> RunStatus {
+        let failed = self.failure_count();
+        RunStatus {
+            passed: self.results.len() - failed,
+            failed,
+            skipped: self.skipped.len(),
+            ..RunStatus::default()
+        }
+    }
+
+    /// compare the dimensions of each slide with a csv export of a previous
+    /// run and return the dimensions that grew by more than the tolerance.
+    /// Only the numeric layout columns are compared, the screenshots differ
+    /// between environments, e.g. by font hinting, even if the layout is the
+    /// same. Slides are matched like in [merge_csv_exports], slides that are
+    /// not in the baseline are ignored. The baseline needs the same unit as
+    /// the results
+    pub fn regressions_from_baseline<R: io::Read>(
+        &self,
+        baseline: R,
+        tolerance: f64,
+    ) -> anyhow::Result<Vec<DimensionRegression>> {
+        let mut csv_reader = csv::Reader::from_reader(baseline);
+        let headers = csv_reader.headers()?.clone();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| anyhow!("baseline without {name} column"))
+        };
+        let (source_dir, filename, fragment) =
+            (column("source_dir")?, column("filename")?, column("fragment")?);
+        let dimension_columns = COMPARED_DIMENSIONS
+            .iter()
+            .map(|name| column(name))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut baseline_values = HashMap::new();
+        for record in csv_reader.records() {
+            let record = record?;
+            let filename = Path::new(&record[filename]);
+            let relative_filename =
+                filename.strip_prefix(&record[source_dir]).unwrap_or(filename);
+            let values = dimension_columns
+                .iter()
+                .map(|column| {
+                    record[*column].parse::<f64>().with_context(|| {
+                        format!(
+                            "invalid dimension in baseline: {:?}",
+                            &record[*column]
+                        )
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            baseline_values.insert(
+                (relative_filename.to_path_buf(), record[fragment].to_string()),
+                values,
+            );
+        }
+
+        let mut regressions = vec![];
+        for result in &self.results {
+            let filename = result
+                .slide
+                .filename
+                .strip_prefix(&result.slide.source_dir)
+                .unwrap_or(&result.slide.filename)
+                .to_path_buf();
+            let fragment = result.slide.fragment.clone().unwrap_or_default();
+            let Some(values) = baseline_values.get(&(filename.clone(), fragment))
+            else {
+                continue;
+            };
+            let current_values = [
+                result.element_size.width,
+                result.element_size.height,
+                result.page_scroll_height,
+            ];
+            for ((field, baseline), current) in
+                COMPARED_DIMENSIONS.iter().zip(values).zip(current_values)
+            {
+                let current = self.dimension(result, current);
+                if current - baseline > tolerance {
+                    regressions.push(DimensionRegression {
+                        filename: filename.clone(),
+                        fragment: result.slide.fragment.clone(),
+                        field,
+                        baseline: *baseline,
+                        current,
+                    });
+                }
+            }
+        }
+        Ok(regressions)
+    }
+
+    /// a single line that groups the failing and skipped slides by reason
+    /// for a quick triage, e.g. "2 slides overflow vertically, 1 slide
+    /// overflows horizontally, 3 skipped (no content)". A slide with several
+    /// policy violations is counted for each of them. None if no slide failed
+    /// or was skipped
+    pub fn failure_banner(&self) -> Option<String> {
+        let mut violation_counts: Vec<(&PolicyViolation, usize)> = vec![];
+        let mut warnings = 0;
+        for result in self.results.iter().filter(|result| self.is_failure(result)) {
+            if result.severity == Severity::Warn {
+                warnings += 1;
+            }
+            for violation in &result.policy_violations {
+                match violation_counts.iter_mut().find(|(v, _)| *v == violation) {
+                    Some((_, count)) => *count += 1,
+                    None => violation_counts.push((violation, 1)),
+                }
+            }
+        }
+        // the most common reasons first, in the order of their occurrence
+        violation_counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let slides = |count: usize, (singular, plural): (&str, &str)| match count {
+            1 => format!("1 slide {singular}"),
+            _ => format!("{count} slides {plural}"),
+        };
+        let mut groups: Vec<String> = violation_counts
+            .into_iter()
+            .map(|(violation, count)| slides(count, violation.banner_phrase()))
+            .collect();
+        if warnings > 0 {
+            groups.push(slides(
+                warnings,
+                ("is close to the maximum size", "are close to the maximum size"),
+            ));
+        }
+        let mut skip_counts: BTreeMap<String, usize> = BTreeMap::new();
+        for skipped in &self.skipped {
+            *skip_counts
+                .entry(skipped.reason.description().to_string())
+                .or_default() += 1;
+        }
+        groups.extend(
+            skip_counts
+                .into_iter()
+                .map(|(reason, count)| format!("{count} skipped ({reason})")),
+        );
+        (!groups.is_empty()).then(|| groups.join(", "))
+    }
+
+    /// print a line per chapter with the number of slides and failures and
+    /// the worst slide to stdout
+    pub fn export_chapters_stdout(&self) {
+        for chapter in self.chapter_summaries() {
+            println!(
+                "{}: {} slides, {} failing, worst {} ({}%)",
+                chapter.chapter,
+                chapter.count,
+                chapter.failures,
+                chapter.worst_slide,
+                chapter.worst_fill_percent
+            );
+        }
+    }
+
+    /// export the screenshots of the results to a PDF file with one slide
+    /// per page, overwrites if allowed. The caption of each page names the
+    /// slide and its size, slides with policy violations have a red caption.
+    /// Results without a screenshot are left out. Returns the number of pages
+    pub fn export_contact_sheet(
+        &self,
+        file: &Path,
+        screenshot_dir: &Path,
+        overwrite: bool,
+    ) -> anyhow::Result<usize> {
+        if file.exists() && !overwrite {
+            Err(anyhow!(
+                "Not allowed to overwrite existing contact sheet at {}",
+                file.display()
+            ))?;
+        };
+
+        let pdf_file = fs::File::create(file)
+            .with_context(|| format!("cannot create {}", file.display()))?;
+        // the screenshots are read one at a time and written right away
+        let mut pdf = ContactSheetWriter::new(io::BufWriter::new(pdf_file))?;
+        let mut pages = 0;
+        for result in &self.results {
+            let Some(screenshot) = &result.screenshot else {
+                continue;
+            };
+            let screenshot = screenshot_dir.join(screenshot);
+            let image = image::open(&screenshot)
+                .with_context(|| format!("cannot read {}", screenshot.display()))?
+                .to_rgb8();
+            let mut caption = format!(
+                "{} {}: {}x{}",
+                result.severity,
+                result.slide,
+                result.element_size.width.round(),
+                result.element_size.height.round()
+            );
+            if !result.policy_violations.is_empty() {
+                let policy_violations: Vec<String> = result
+                    .policy_violations
+                    .iter()
+                    .map(PolicyViolation::to_string)
+                    .collect();
+                caption.push_str(&format!(" [{}]", policy_violations.join(";")));
+            }
+            pdf.add_page(&ContactSheetPage {
+                image,
+                caption,
+                highlight: !result.policy_violations.is_empty(),
+            })?;
+            pages += 1;
+        }
+        pdf.finish()?;
+        Ok(pages)
+    }
+
+    /// write index.json to screenshot_dir. It maps the path of each slide
+    /// with a screenshot (relative to the common source directory, with the
+    /// fragment if any) to the path of its screenshot relative to
+    /// screenshot_dir. The index is written to a temporary file first and
+    /// renamed, so readers never see a partial index. Returns the number of
+    /// entries
+    pub fn export_screenshot_index(
+        &self,
+        screenshot_dir: &Path,
+    ) -> anyhow::Result<usize> {
+        let base_dir = self.book.common_source_dir();
+        let mut index = BTreeMap::new();
+        for result in &self.results {
+            let Some(screenshot) = &result.screenshot else {
+                continue;
+            };
+            let mut slide = result
+                .slide
+                .filename
+                .strip_prefix(&base_dir)
+                .unwrap_or(&result.slide.filename)
+                .to_string_lossy()
+                .into_owned();
+            if let Some(fragment) = &result.slide.fragment {
+                slide.push_str(&format!("#{fragment}"));
+            }
+            index.insert(slide, screenshot.clone());
+        }
+        let file = screenshot_dir.join("index.json");
+        let temporary_file = screenshot_dir.join("index.json.tmp");
+        fs::write(&temporary_file, serde_json::to_vec_pretty(&index)?)
+            .with_context(|| format!("cannot write {}", temporary_file.display()))?;
+        fs::rename(&temporary_file, &file)
+            .with_context(|| format!("cannot write {}", file.display()))?;
+        Ok(index.len())
+    }
+
+    /// export the height histogram to the given csv file, overwrites if
+    /// allowed
+    pub fn export_histogram_csv(
+        &self,
+        file: &Path,
+        overwrite: bool,
+        bucket_size: usize,
+    ) -> anyhow::Result<()> {
+        if file.exists() && !overwrite {
+            Err(anyhow!(
+                "Not allowed to overwrite existing histogram at {}",
+                file.display()
+            ))?;
+        };
+
+        let mut csv_writer = csv::Writer::from_path(file)
+            .with_context(|| format!("cannot create {}", file.display()))?;
+        for bucket in self.height_histogram(bucket_size) {
+            csv_writer.serialize(bucket)?;
+        }
+        Ok(())
+    }
+
+    /// print the height histogram as ascii bars to stdout
+    pub fn export_histogram_stdout(&self, bucket_size: usize) {
+        const MAX_BAR_LENGTH: usize = 60;
+        let histogram = self.height_histogram(bucket_size);
+        let max_count = histogram.iter().map(|bucket| bucket.count).max();
+        for bucket in &histogram {
+            // scale the bars down if the largest bucket does not fit
+            let bar_length = match max_count {
+                Some(max_count) if max_count > MAX_BAR_LENGTH => {
+                    bucket.count * MAX_BAR_LENGTH / max_count
+                }
+                _ => bucket.count,
+            };
+            println!(
+                "{:>6}-{:<6} {:>5} {}",
+                bucket.min_height,
+                bucket.max_height,
+                bucket.count,
+                "#".repeat(bar_length)
+            );
+        }
+    }
+
+    /// write the results as tab separated values without a header. Each line
+    /// is path, width, height and severity of a slide
+    pub fn export_tsv<W: io::Write>(
+        &self,
+        mut writer: W,
+        violations_only: bool,
+        rounding: RoundingMode,
+    ) -> anyhow::Result<()> {
+        for result in self.exported_results(violations_only) {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}",
+                result.slide,
+                rounding.apply(self.dimension(result, result.element_size.width)),
+                rounding.apply(self.dimension(result, result.element_size.height)),
+                result.severity,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// write a few lines per slide that explain its severity: the measured
+    /// size, the thresholds of the slide policy that the slide was evaluated
+    /// with including a maximum height from a class or the derived height
+    /// threshold, the fill ratio and the reason of each policy violation.
+    /// Sizes are in pixels as measured
+    pub fn export_explanation<W: io::Write>(
+        &self,
+        mut writer: W,
+        violations_only: bool,
+    ) -> anyhow::Result<()> {
+        for result in self.exported_results(violations_only) {
+            let size = &result.element_size;
+            let slide_policy = self.slide_policy_of(result);
+            writeln!(writer, "{}: {}", result.slide, result.severity)?;
+            writeln!(
+                writer,
+                "  measured {}x{}, page height {}",
+                size.width, size.height, result.page_scroll_height
+            )?;
+            let height_source =
+                match (result.max_height_override, self.height_threshold) {
+                    (Some(_), _) => String::from(" (from a class of the element)"),
+                    (None, Some(height_threshold)) => format!(
+                        " (the {}th percentile of the slide heights)",
+                        height_threshold.percentile
+                    ),
+                    (None, None) => String::new(),
+                };
+            writeln!(
+                writer,
+                "  maximum {}x{}{}, fails above {}% ({}x{}), warns from {}%",
+                slide_policy.max_width,
+                slide_policy.max_height,
+                height_source,
+                slide_policy.fail_percent,
+                slide_policy.width_limit(),
+                slide_policy.height_limit(),
+                slide_policy.warn_percent
+            )?;
+            writeln!(
+                writer,
+                "  fills {:.0}% of the maximum size",
+                result.fill_ratio * 100.0
+            )?;
+            for violation in &result.policy_violations {
+                let reason = match violation {
+                    PolicyViolation::MaxWidth => format!(
+                        "width {} is above {}",
+                        size.width,
+                        slide_policy.width_limit()
+                    ),
+                    PolicyViolation::MaxHeight => format!(
+                        "height {} is above {}",
+                        size.height,
+                        slide_policy.height_limit()
+                    ),
+                    PolicyViolation::ConsoleErrors => {
+                        format!("{} console errors", result.console_errors.len())
+                    }
+                    PolicyViolation::RenderTime => format!(
+                        "render time {:?} is above {:?}",
+                        result.render_time.unwrap_or_default(),
+                        slide_policy.max_render_time.unwrap_or_default()
+                    ),
+                    PolicyViolation::ScreenshotSize => format!(
+                        "screenshot is larger than {} bytes",
+                        slide_policy.max_screenshot_size.unwrap_or_default()
+                    ),
+                    PolicyViolation::ClippedText => format!(
+                        "{} elements with clipped text",
+                        result.clipped_elements
+                    ),
+                    PolicyViolation::HeightOutlier => format!(
+                        "height {} is above {}, the height threshold of the book",
+                        size.height,
+                        slide_policy.height_limit()
+                    ),
+                    PolicyViolation::BelowFold => format!(
+                        "bottom {} is below the fold at {}",
+                        size.y + size.height,
+                        slide_policy.fold.unwrap_or_default()
+                    ),
+                };
+                writeln!(writer, "  {violation}: {reason}")?;
+            }
+            let verdict = match result.severity {
+                Severity::Fail => {
+                    String::from("fails because of the violations above")
+                }
+                Severity::Warn => format!(
+                    "warns as it fills at least {}% of the maximum size{}",
+                    slide_policy.warn_percent,
+                    if self.warnings_as_errors {
+                        ", warnings are failures"
+                    } else {
+                        ""
+                    }
+                ),
+                Severity::Pass => format!(
+                    "passes as it fills less than {}% of the maximum size",
+                    slide_policy.warn_percent
+                ),
+            };
+            writeln!(writer, "  {verdict}")?;
+        }
+        Ok(())
+    }
+
+    /// dump the results to stdout. Dimensions are rounded with the given
+    /// rounding mode. The position of the element is added if verbose is set.
+    /// Failing slides with a failure message are reported with the message
+    pub fn export_stdout(
+        &self,
+        violations_only: bool,
+        rounding: RoundingMode,
+        verbose: bool,
+    ) {
+        for result in self.exported_results(violations_only) {
+            if let Some(failure_message) = self.failure_message(result, rounding) {
+                println!("{} {}", result.severity, failure_message);
+                continue;
+            }
+            let position = if verbose {
+                format!(
+                    " at ({}, {})",
+                    rounding.apply(self.dimension(result, result.element_size.x)),
+                    rounding.apply(self.dimension(result, result.element_size.y))
+                )
+            } else {
+                String::new()
+            };
+            println!(
+                "{} {}: {}x{}{} overflow {}x{} [{}]{}{}{}{}",
+                result.severity,
+                result.slide,
+                rounding.apply(self.dimension(result, result.element_size.width)),
+                rounding.apply(self.dimension(result, result.element_size.height)),
+                position,
+                rounding.apply(self.dimension(result, result.overflow.horizontal)),
+                rounding.apply(self.dimension(result, result.overflow.vertical)),
+                result
+                    .policy_violations
+                    .iter()
+                    .map(PolicyViolation::to_string)
+                    .collect::<Vec<_>>()
+                    .join(";"),
+                if result.unstable { " unstable" } else { "" },
+                if result.blank { " blank" } else { "" },
+                match result.retries {
+                    0 => String::new(),
+                    retries => format!(" retries {retries}"),
+                },
+                if result.estimated { " estimated" } else { "" },
+            );
+        }
+    }
+}
+
+/// merge csv exports of partial runs into a single csv, e.g. of a book that
+/// is evaluated in shards on several machines. Records are identified by the
+/// slide path relative to its source directory and the fragment, so shards
+/// with different checkout locations merge. A later input replaces the
+/// record of the same slide in an earlier one. All inputs need the same
+/// columns. Returns the number of merged records
+pub fn merge_csv_exports<R: io::Read, W: io::Write>(
+    inputs: Vec<R>,
+    writer: W,
+) -> anyhow::Result<usize> {
+    let mut headers: Option<csv::StringRecord> = None;
+    let mut keys = vec![];
+    let mut records: HashMap<(PathBuf, String), csv::StringRecord> = HashMap::new();
+    for input in inputs {
+        let mut csv_reader = csv::Reader::from_reader(input);
+        let input_headers = csv_reader.headers()?.clone();
+        match &headers {
+            Some(headers) if *headers != input_headers => Err(anyhow!(
+                "csv exports have different columns: {:?}",
+                input_headers
+            ))?,
+            Some(_) => {}
+            None => headers = Some(input_headers.clone()),
+        }
+        let column = |name: &str| {
+            input_headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| anyhow!("csv export without {name} column"))
+        };
+        let (source_dir, filename, fragment) =
+            (column("source_dir")?, column("filename")?, column("fragment")?);
+        for record in csv_reader.records() {
+            let record = record?;
+            let filename = Path::new(&record[filename]);
+            let relative_filename =
+                filename.strip_prefix(&record[source_dir]).unwrap_or(filename);
+            let key =
+                (relative_filename.to_path_buf(), record[fragment].to_string());
+            if records.insert(key.clone(), record).is_none() {
+                keys.push(key);
+            }
+        }
+    }
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    if let Some(headers) = &headers {
+        csv_writer.write_record(headers)?;
+    }
+    for key in &keys {
+        csv_writer.write_record(&records[key])?;
+    }
+    csv_writer.flush()?;
+    Ok(keys.len())
+}
+
+/// the columns of the exports compared with a baseline in the order of
+/// [EvaluationResults::regressions_from_baseline]
+const COMPARED_DIMENSIONS: [&str; 3] =
+    ["element_width", "element_height", "page_scroll_height"];
+
+/// a dimension of a slide that grew compared to the baseline
+#[derive(Debug)]
+pub struct DimensionRegression {
+    /// the path of the slide relative to its source directory
+    pub filename: PathBuf,
+    /// the fragment of the slide if the page contains multiple slides
+    pub fragment: Option<String>,
+    /// the name of the column, e.g. element_height
+    pub field: &'static str,
+    /// the value in the baseline
+    pub baseline: f64,
+    /// the value of this run
+    pub current: f64,
+}
+
+impl fmt::Display for DimensionRegression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.filename.display())?;
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{fragment}")?;
+        }
+        write!(f, ": {} grew from {} to {}", self.field, self.baseline, self.current)
+    }
+}
+
+/// the tables of the SQL export
+const SQL_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS runs (
+    run_id TEXT PRIMARY KEY,
+    label TEXT,
+    timestamp TEXT NOT NULL,
+    slides INTEGER NOT NULL,
+    failures INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS slide_results (
+    run_id TEXT NOT NULL REFERENCES runs (run_id),
+    slide_id TEXT,
+    filename TEXT NOT NULL,
+    fragment TEXT,
+    element_width REAL NOT NULL,
+    element_height REAL NOT NULL,
+    page_scroll_height REAL NOT NULL,
+    max_code_lines INTEGER NOT NULL,
+    render_time_ms INTEGER,
+    severity TEXT NOT NULL,
+    policy_violations TEXT NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS slide_results_slide
+    ON slide_results (run_id, filename, IFNULL(fragment, ''));
+CREATE INDEX IF NOT EXISTS slide_results_slide_id ON slide_results (slide_id);
+";
+
+/// the value as an SQL string literal
+fn sql_text(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// the default XPath to the content element of an mdbook page
+pub const DEFAULT_ELEMENT_SELECTOR: &str = r#"//*[@id="content"]/main"#;
+
+/// the id of the style element with the extra css
+const EXTRA_CSS_ID: &str = "slide-evaluator-extra-css";
+
+/// the key of an element reference in the json of the webdriver protocol
+const WEB_ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735466cecf";
+
+/// returns the section of a slide that starts at the anchor element
+/// (arguments[0]) of its fragment. The section of a heading, or of an anchor
+/// inside a heading, ends before the next sibling heading of the same or a
+/// higher level, or at the end of the parent. It is wrapped into a div with
+/// its own block formatting context, so the margins of the section are part
+/// of its size. Running the script again returns the same div. Other anchors
+/// are returned as they are, e.g. the section elements of a single file
+const FRAGMENT_SECTION_SCRIPT: &str = script!(
+    "fragment-section",
+    r"const [anchor] = arguments;
+const heading = anchor.closest('h1, h2, h3, h4, h5, h6');
+if (!heading) {
+    return anchor;
+}
+if (heading.parentElement.dataset.slideEvaluatorSection !== undefined) {
+    return heading.parentElement;
+}
+const level = Number(heading.tagName[1]);
+const section = document.createElement('div');
+section.dataset.slideEvaluatorSection = '';
+section.style.display = 'flow-root';
+heading.before(section);
+let node = heading;
+while (node) {
+    const next = node.nextSibling;
+    section.append(node);
+    node = next;
+    if (node && /^H[1-6]$/.test(node.tagName) && Number(node.tagName[1]) <= level) {
+        break;
+    }
+}
+return section;"
+);
+
+/// measures the page and the element (arguments[0]) of the opened slide:
+/// - the scroll height of the whole page in the current frame
+/// - the ratio of device pixels to css pixels of the browser
+/// - the computed font size of the root element in pixels
+/// - the time from the start of the navigation until the load event of the
+///   page ended, null for pages without a navigation timing (e.g. written
+///   by a script)
+/// - the number of lines of the longest code block (pre element) in the
+///   element. Slides with long code blocks are candidates for splitting
+/// - the number of elements in the element whose content is wider than the
+///   element and cut off instead of scrollable, e.g. text truncated with an
+///   ellipsis. The dimensions of such elements fit, but content is lost
+/// - the content box of the element if arguments[1] is true: its client
+///   size, which excludes borders and scrollbars, without the padding
+/// - the class attribute of the element
+/// - the DOM of the page if arguments[2] is true
+/// - the number of rules the browser parsed from the extra css in the style
+///   element with the id arguments[3]. Invalid rules are dropped silently by
+///   the browser. null if the style sheet cannot be inspected, e.g. from a
+///   cross-origin frame
+const MEASUREMENT_SCRIPT: &str = script!(
+    "measure",
+    r"const [element, measureContentBox, dumpDom, extraCssId] = arguments;
+const navigation = performance.getEntriesByType('navigation')[0];
+let contentBox = null;
+if (measureContentBox) {
+    const style = getComputedStyle(element);
+    contentBox = [
+        element.clientWidth
+            - parseFloat(style.paddingLeft) - parseFloat(style.paddingRight),
+        element.clientHeight
+            - parseFloat(style.paddingTop) - parseFloat(style.paddingBottom),
+    ];
+}
+let extraCssApplied = null;
+if (extraCssId) {
+    try {
+        const style = window.top.document.getElementById(extraCssId);
+        extraCssApplied = Boolean(style && style.sheet && !style.sheet.disabled
+            && style.sheet.cssRules.length === Number(style.dataset.rules));
+    } catch (error) {}
+}
+return {
+    pageScrollHeight: document.documentElement.scrollHeight || 0,
+    devicePixelRatio: window.devicePixelRatio || 1,
+    rootFontSize:
+        parseFloat(getComputedStyle(document.documentElement).fontSize) || 16,
+    renderTime: navigation && navigation.loadEventEnd > 0
+        ? navigation.loadEventEnd - navigation.startTime
+        : null,
+    maxCodeLines: Math.max(0, ...Array.from(element.querySelectorAll('pre'))
+        .map(pre => pre.textContent.replace(/\n$/, '').split('\n').length)),
+    clippedElements: Array.from(element.querySelectorAll('*')).filter(e =>
+        e.scrollWidth > e.clientWidth
+        && ['hidden', 'clip'].includes(getComputedStyle(e).overflowX)
+    ).length,
+    contentBox,
+    classAttribute: element.getAttribute('class'),
+    dom: dumpDom ? document.documentElement.outerHTML : null,
+    extraCssApplied,
+};"
+);
+
+/// the result of [MEASUREMENT_SCRIPT]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageMeasurements {
+    page_scroll_height: f64,
+    device_pixel_ratio: f64,
+    root_font_size: f64,
+    /// in milliseconds
+    render_time: Option<f64>,
+    max_code_lines: usize,
+    clipped_elements: usize,
+    content_box: Option<(f64, f64)>,
+    class_attribute: Option<String>,
+    dom: Option<String>,
+    /// None without extra css
+    extra_css_applied: Option<bool>,
+}
+
+/// a web font that is loaded into each slide before measuring it
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebFont {
+    /// the font family name used by the css of the slides
+    pub family: String,
+    /// the url of the font file, e.g. a data uri
+    pub url: String,
+}
+
+impl WebFont {
+    /// a font from a local file, embedded as data uri so the browser does
+    /// not need access to the file. Supports woff2, woff, ttf and otf files
+    pub fn from_file(family: &str, file: &Path) -> anyhow::Result<WebFont> {
+        let mime_type =
+            match file.extension().and_then(|extension| extension.to_str()) {
+                Some("woff2") => "font/woff2",
+                Some("woff") => "font/woff",
+                Some("ttf") => "font/ttf",
+                Some("otf") => "font/otf",
+                _ => Err(anyhow!("unsupported font file {}", file.display()))?,
+            };
+        let content = fs::read(file)
+            .with_context(|| format!("cannot read {}", file.display()))?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(content);
+        Ok(WebFont {
+            family: family.to_string(),
+            url: format!("data:{mime_type};base64,{encoded}"),
+        })
+    }
+}
+
+/// the @font-face rules of the fonts. The browser waits for the fonts
+/// instead of rendering with a fallback font
+fn font_face_css(fonts: &[WebFont]) -> String {
+    let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+    fonts
+        .iter()
+        .map(|font| {
+            format!(
+                "@font-face {{ font-family: \"{}\"; src: url(\"{}\"); \
+                 font-display: block; }}\n",
+                escape(&font.family),
+                escape(&font.url)
+            )
+        })
+        .collect()
+}
+
+/// append the path to the path of the base url. Unlike Url::join, the path
+/// of the base url is kept whether or not it ends with a slash, e.g.
+/// https://host/docs and https://host/docs/ with intro.html both result in
+/// https://host/docs/intro.html. The segments of the path are percent-encoded,
+/// parent directories do not leave the path of the base url. The query and
+/// fragment of the base url are dropped
+fn join_url_path(base_url: &Url, path: &Path) -> anyhow::Result<Url> {
+    let mut url = base_url.clone();
+    url.set_query(None);
+    url.set_fragment(None);
+    {
+        let mut segments = url
+            .path_segments_mut()
+            .map_err(|_| anyhow!("{base_url} cannot be a base url"))?;
+        segments.pop_if_empty();
+        // the number of segments pushed on top of the base path
+        let mut depth = 0;
+        for component in path.components() {
+            match component {
+                Component::Normal(segment) => {
+                    let segment = segment
+                        .to_str()
+                        .ok_or_else(|| anyhow!("invalid path {}", path.display()))?;
+                    segments.push(segment);
+                    depth += 1;
+                }
+                Component::ParentDir if depth > 0 => {
+                    segments.pop();
+                    depth -= 1;
+                }
+                Component::ParentDir
+                | Component::CurDir
+                | Component::RootDir
+                | Component::Prefix(_) => {}
+            }
+        }
+    }
+    Ok(url)
+}
+
+/// whether the browser reports running headless as a capability, like
+/// Firefox does
+fn headless_capability(webclient: &Client) -> Option<bool> {
+    webclient
+        .capabilities()
+        .and_then(|capabilities| capabilities.get("moz:headless"))
+        .map(|headless| headless.as_bool() == Some(true))
+}
+
+/// detect if the browser runs headless. Firefox reports this as a
+/// capability, headless Chromium reports it in the user agent
+async fn is_headless(webclient: &Client) -> anyhow::Result<bool> {
+    if let Some(headless) = headless_capability(webclient) {
+        return Ok(headless);
+    }
+    let user_agent = webclient
+        .execute(script!("user-agent", "return navigator.userAgent"), vec![])
+        .await?;
+    Ok(user_agent.as_str().is_some_and(|user_agent| user_agent.contains("Headless")))
+}
+
+/// builds an Evaluator. Only the webdriver session and the source_dir are
+/// required, all other options have defaults
+pub struct EvaluatorBuilder<'a> {
+    /// the webdriver session used to render the slides
+    webdriver_session: WebdriverSession,
+    /// the window size of the webclient
+    window_size: (u32, u32),
+    /// XPath to the element that is scored
+    element_selector: &'a str,
+    /// store screenshot in this directory if provided
+    screenshot_dir: Option<PathBuf>,
+    /// draw the maximum slide size into the screenshots
+    screenshot_overlay: bool,
+    /// scale the screenshots from device pixels down to css pixels
+    normalize_dpr: bool,
+    /// flag slides whose screenshot is (nearly) a single color
+    detect_blank: bool,
+    /// measure the content box of the element without its padding in
+    /// addition to its rectangle
+    measure_content_box: bool,
+    /// how the paths of the output files are derived from the slide paths
+    output_layout: OutputLayout,
+    /// store the html of each evaluated slide in this directory if provided
+    html_dump_dir: Option<PathBuf>,
+    /// store the live DOM of each evaluated slide after rendering in this
+    /// directory if provided
+    dom_dump_dir: Option<PathBuf>,
+    /// html base uri to the source_dir used as a prefix for each page
+    html_base_url: Url,
+    /// serve source_dir with a local http server instead of html_base_url
+    serve_source_dir: bool,
+    /// username and password for basic authentication against html_base_url
+    basic_auth: Option<(String, String)>,
+    /// additional http headers sent with every request of the browser
+    http_headers: Vec<(String, String)>,
+    /// the user agent string reported by the browser if provided
+    user_agent: Option<String>,
+    /// the locale of the browser if provided
+    locale: Option<String>,
+    /// the timezone of the browser if provided
+    timezone: Option<String>,
+    /// base directory for all processed files. the output files mirror the
+    /// layout of the slides relative to it
+    source_dir: PathBuf,
+    /// if this token is cancelled, the process needs to end gracefully
+    cancellation_token: CancellationToken,
+    /// the policy applied to the slides
+    slide_policy: SlidePolicy,
+    /// evaluate at most this many slides if provided
+    max_slides: Option<usize>,
+    /// evaluate a slide up to this many more times if the evaluation fails
+    max_retries: usize,
+    /// how console errors of the slides are handled
+    console_error_policy: ConsoleErrorPolicy,
+    /// how slides without the evaluated element are handled
+    missing_element_policy: MissingElementPolicy,
+    /// elements with a smaller area in square pixels count as not found
+    min_element_area: f64,
+    /// switch to this frame before looking for the element if provided
+    frame: Option<Frame>,
+    /// measure each slide twice and flag differences above this tolerance
+    /// in pixels if provided
+    stability_tolerance: Option<f64>,
+    /// measure the element with an injected script instead of the webdriver
+    /// rectangle command
+    script_measurement: bool,
+    /// fail if the browser is not running headless
+    require_headless: bool,
+    /// fail if the window size cannot be set instead of keeping the window
+    /// size of the browser
+    require_window_size: bool,
+    /// wait this long after opening a slide before measuring it if provided
+    settle_delay: Option<Duration>,
+    /// emulate prefers-reduced-motion: reduce in the browser
+    reduced_motion: bool,
+    /// zoom the pages by this factor before measuring if provided
+    zoom: Option<f64>,
+    /// javascript executed in each slide before measuring if provided
+    setup_script: Option<String>,
+    /// javascript that reveals the next fragment of a slide and returns true,
+    /// or returns false if all fragments are revealed, if provided
+    fragment_script: Option<String>,
+    /// css added to each slide before measuring if provided
+    extra_css: Option<String>,
+    /// web fonts loaded into each slide before measuring
+    fonts: Vec<WebFont>,
+    /// wait for the document to be completely loaded before measuring
+    wait_for_ready_state: bool,
+    /// the page load and script timeout of the browser if provided
+    navigation_timeout: Option<Duration>,
+    /// the implicit wait of the browser for element lookups if provided
+    find_timeout: Option<Duration>,
+    /// the custom metrics measured for each slide
+    metrics: MetricRegistry,
+    /// append each result to this jsonl file as soon as it is measured if
+    /// provided
+    incremental_export: Option<PathBuf>,
+    /// slow down the CPU of the browser by this factor if provided
+    cpu_throttle: Option<f64>,
+    /// emulate this network connection if provided
+    network_throttle: Option<NetworkThrottle>,
+}
+
+impl<'a> EvaluatorBuilder<'a> {
+    /// create a builder for an evaluator that renders the slides in
+    /// source_dir with the given webdriver session
+    pub fn new(webdriver_session: WebdriverSession, source_dir: PathBuf) -> Self {
+        EvaluatorBuilder {
+            webdriver_session,
+            window_size: (1920, 1080),
+            element_selector: DEFAULT_ELEMENT_SELECTOR,
+            screenshot_dir: None,
+            screenshot_overlay: false,
+            normalize_dpr: false,
+            detect_blank: false,
+            measure_content_box: false,
+            output_layout: OutputLayout::Mirror,
+            html_dump_dir: None,
+            dom_dump_dir: None,
+            html_base_url: Url::parse("file:///").unwrap(),
+            serve_source_dir: false,
+            basic_auth: None,
+            http_headers: vec![],
+            user_agent: None,
+            locale: None,
+            timezone: None,
+            source_dir,
+            cancellation_token: CancellationToken::new(),
+            slide_policy: SlidePolicy::default(),
+            max_slides: None,
+            max_retries: 0,
+            console_error_policy: ConsoleErrorPolicy::Ignore,
+            missing_element_policy: MissingElementPolicy::Skip,
+            min_element_area: 0.0,
+            frame: None,
+            stability_tolerance: None,
+            script_measurement: false,
+            require_headless: false,
+            require_window_size: false,
+            settle_delay: None,
+            reduced_motion: false,
+            zoom: None,
+            setup_script: None,
+            fragment_script: None,
+            extra_css: None,
+            fonts: vec![],
+            wait_for_ready_state: true,
+            navigation_timeout: None,
+            find_timeout: None,
+            metrics: MetricRegistry::default(),
+            incremental_export: None,
+            cpu_throttle: None,
+            network_throttle: None,
+        }
+    }
+
+    /// set the window size (width, height) of the webclient
+    pub fn window_size(mut self, width: u32, height: u32) -> Self {
+        self.window_size = (width, height);
+        self
+    }
+
+    /// set the XPath to the element that is evaluated
+    pub fn element_selector(mut self, element_selector: &'a str) -> Self {
+        self.element_selector = element_selector;
+        self
+    }
+
+    /// take screenshots of the content element and store them in this
+    /// directory
+    pub fn screenshot_dir(mut self, screenshot_dir: Option<PathBuf>) -> Self {
+        self.screenshot_dir = screenshot_dir;
+        self
+    }
+
+    /// draw a rectangle with the maximum slide size of the slide policy into
+    /// the screenshots, so content beyond the limit is easy to spot
+    pub fn screenshot_overlay(mut self, screenshot_overlay: bool) -> Self {
+        self.screenshot_overlay = screenshot_overlay;
+        self
+    }
+
+    /// scale the screenshots down by the device pixel ratio of the browser,
+    /// so runners with a HiDPI display store screenshots of the same size as
+    /// runners without one. The measured dimensions are css pixels anyway
+    pub fn normalize_dpr(mut self, normalize_dpr: bool) -> Self {
+        self.normalize_dpr = normalize_dpr;
+        self
+    }
+
+    /// flag slides whose screenshot is (nearly) a single color as blank. This
+    /// takes a screenshot of each slide even without a screenshot directory
+    pub fn detect_blank(mut self, detect_blank: bool) -> Self {
+        self.detect_blank = detect_blank;
+        self
+    }
+
+    /// measure the content box of the element, its client size without
+    /// padding, in addition to its rectangle. This tells overflow caused by
+    /// padding apart from overflow caused by content
+    pub fn measure_content_box(mut self, measure_content_box: bool) -> Self {
+        self.measure_content_box = measure_content_box;
+        self
+    }
+
+    /// set how the paths of the screenshots and html dumps are derived from
+    /// the paths of the slides
+    pub fn output_layout(mut self, output_layout: OutputLayout) -> Self {
+        self.output_layout = output_layout;
+        self
+    }
+
+    /// store the html of each evaluated slide in this directory
+    pub fn html_dump_dir(mut self, html_dump_dir: Option<PathBuf>) -> Self {
+        self.html_dump_dir = html_dump_dir;
+        self
+    }
+
+    /// store the DOM of each evaluated slide in this directory as it is
+    /// measured, i.e. after the scripts of the page, the setup script and the
+    /// fragment script ran. Unlike the html dump this shows expanded content
+    /// and injected styles. The directory layout is the same as for the
+    /// screenshots
+    pub fn dom_dump_dir(mut self, dom_dump_dir: Option<PathBuf>) -> Self {
+        self.dom_dump_dir = dom_dump_dir;
+        self
+    }
+
+    /// set the base url that is used to render the files (relative to
+    /// source_dir)
+    pub fn html_base_url(mut self, html_base_url: Url) -> Self {
+        self.html_base_url = html_base_url;
+        self
+    }
+
+    /// serve source_dir with a local http server on a free port and render
+    /// the slides from it instead of the html_base_url. The browser has to
+    /// run on the same machine. The server stops with the evaluator
+    pub fn serve_source_dir(mut self, serve_source_dir: bool) -> Self {
+        self.serve_source_dir = serve_source_dir;
+        self
+    }
+
+    /// authenticate with username and password (basic authentication) when
+    /// opening the slides. the credentials are added to html_base_url
+    pub fn basic_auth(mut self, basic_auth: Option<(String, String)>) -> Self {
+        self.basic_auth = basic_auth;
+        self
+    }
+
+    /// send these http headers with every request of the browser. This
+    /// requires a Chromium based browser
+    pub fn http_headers(mut self, http_headers: Vec<(String, String)>) -> Self {
+        self.http_headers = http_headers;
+        self
+    }
+
+    /// report this user agent string to the slides instead of the default of
+    /// the browser. This requires a Chromium based browser
+    pub fn user_agent(mut self, user_agent: Option<String>) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
+
+    /// render the slides with this locale (e.g. en-US) instead of the locale
+    /// of the runner, so localized dates and numbers have the same width
+    /// everywhere. This requires a Chromium based browser
+    pub fn locale(mut self, locale: Option<String>) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// render the slides in this IANA timezone (e.g. UTC) instead of the
+    /// timezone of the runner. This requires a Chromium based browser
+    pub fn timezone(mut self, timezone: Option<String>) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// set the token that ends the evaluation gracefully once cancelled
+    pub fn cancellation_token(
+        mut self,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// set the policy applied to the slides
+    pub fn slide_policy(mut self, slide_policy: SlidePolicy) -> Self {
+        self.slide_policy = slide_policy;
+        self
+    }
+
+    /// evaluate only the first max_slides slides of the book
+    pub fn max_slides(mut self, max_slides: Option<usize>) -> Self {
+        self.max_slides = max_slides;
+        self
+    }
+
+    /// retry the evaluation of a slide up to max_retries times if it fails,
+    /// e.g. because of a flaky webdriver. The retries are recorded with the
+    /// results
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// set how console errors of the slides are handled
+    pub fn console_error_policy(mut self, policy: ConsoleErrorPolicy) -> Self {
+        self.console_error_policy = policy;
+        self
+    }
+
+    /// set how slides without the evaluated element are handled
+    pub fn missing_element_policy(mut self, policy: MissingElementPolicy) -> Self {
+        self.missing_element_policy = policy;
+        self
+    }
+
+    /// treat elements with an area below min_element_area square pixels as
+    /// not found, e.g. empty placeholders that match the selector
+    pub fn min_element_area(mut self, min_element_area: f64) -> Self {
+        self.min_element_area = min_element_area;
+        self
+    }
+
+    /// evaluate the element inside this frame of the page, e.g. an iframe
+    /// with an interactive demo
+    pub fn frame(mut self, frame: Option<Frame>) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    /// measure each slide a second time and flag slides as unstable whose
+    /// measurements differ by more than the tolerance in pixels
+    pub fn stability_tolerance(mut self, tolerance: Option<f64>) -> Self {
+        self.stability_tolerance = tolerance;
+        self
+    }
+
+    /// measure the elements with getBoundingClientRect in an injected
+    /// script instead of the webdriver rectangle command, e.g. for drivers
+    /// with a slow or unsupported rectangle command. Both report the size in
+    /// css pixels and the position relative to the document
+    pub fn script_measurement(mut self, script_measurement: bool) -> Self {
+        self.script_measurement = script_measurement;
+        self
+    }
+
+    /// fail if the browser is not running headless
+    pub fn require_headless(mut self, require_headless: bool) -> Self {
+        self.require_headless = require_headless;
+        self
+    }
+
+    /// fail if the window size cannot be set. Some headless drivers do not
+    /// support resizing the window, by default the evaluator then logs a
+    /// warning and keeps the window size the browser started with
+    pub fn require_window_size(mut self, require_window_size: bool) -> Self {
+        self.require_window_size = require_window_size;
+        self
+    }
+
+    /// wait this long after opening a slide before measuring it, e.g. to let
+    /// entrance animations of a theme finish
+    pub fn settle_delay(mut self, settle_delay: Option<Duration>) -> Self {
+        self.settle_delay = settle_delay;
+        self
+    }
+
+    /// zoom the pages by this factor (e.g. 1.25 for 125%) before measuring,
+    /// so the measurements match a display that shows the slides zoomed
+    pub fn zoom(mut self, zoom: Option<f64>) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    /// wait until the document ready state of each slide is complete before
+    /// measuring it, so pages with scripts or slow assets are not measured
+    /// with a partial layout. Enabled by default
+    pub fn wait_for_ready_state(mut self, wait_for_ready_state: bool) -> Self {
+        self.wait_for_ready_state = wait_for_ready_state;
+        self
+    }
+
+    /// fail the evaluation of a slide if opening its page takes longer than
+    /// this, e.g. because a served book does not respond. The browser
+    /// enforces it as page load timeout and as script timeout, e.g. for
+    /// loading the fonts. The timeout is independent of the find timeout
+    pub fn navigation_timeout(
+        mut self,
+        navigation_timeout: Option<Duration>,
+    ) -> Self {
+        self.navigation_timeout = navigation_timeout;
+        self
+    }
+
+    /// wait up to this long for the evaluated element to appear after the
+    /// page was opened, e.g. for pages that render their content with a
+    /// script. The browser enforces it as implicit wait of every element
+    /// lookup. A slide whose element does not appear in time is skipped
+    pub fn find_timeout(mut self, find_timeout: Option<Duration>) -> Self {
+        self.find_timeout = find_timeout;
+        self
+    }
+
+    /// write each result to this jsonl file as soon as the slide is
+    /// measured, so a crash or cancelled run leaves a partial report. The
+    /// records are not rounded, have no label and are in pixels. The file is
+    /// replaced at the start of eval_book
+    pub fn incremental_export(
+        mut self,
+        incremental_export: Option<PathBuf>,
+    ) -> Self {
+        self.incremental_export = incremental_export;
+        self
+    }
+
+    /// measure these custom metrics for each slide in addition to the
+    /// registered ones. The values are exported with the results
+    pub fn metrics(
+        mut self,
+        metrics: impl IntoIterator<Item = Box<dyn SlideMetric>>,
+    ) -> Self {
+        for metric in metrics {
+            self.metrics.register(metric);
+        }
+        self
+    }
+
+    /// add this css to each slide after opening it and before measuring it,
+    /// e.g. to evaluate the slides with a larger font without rebuilding the
+    /// book with another theme. It is added after the styles of the page and
+    /// takes precedence over rules of the same specificity
+    pub fn extra_css(mut self, extra_css: Option<String>) -> Self {
+        self.extra_css = extra_css;
+        self
+    }
+
+    /// load these web fonts into each slide after opening it and wait until
+    /// they are loaded before measuring it, e.g. if the fonts of the theme
+    /// are not available to a headless browser. Fonts that fail to load are
+    /// reported and the browser renders with a fallback font
+    pub fn fonts(mut self, fonts: Vec<WebFont>) -> Self {
+        self.fonts = fonts;
+        self
+    }
+
+    /// execute this javascript in each slide after opening it and before
+    /// measuring it, e.g. to expand all collapsed elements so the full
+    /// content is measured
+    pub fn setup_script(mut self, setup_script: Option<String>) -> Self {
+        self.setup_script = setup_script;
+        self
+    }
+
+    /// step through the fragments of each slide with this javascript and
+    /// measure the element after each step, e.g. for decks that reveal one
+    /// list item at a time. The script reveals the next fragment and returns
+    /// true, or returns false if there is none. The largest size across all
+    /// fragments is reported
+    pub fn fragment_script(mut self, fragment_script: Option<String>) -> Self {
+        self.fragment_script = fragment_script;
+        self
+    }
+
+    /// emulate prefers-reduced-motion: reduce, so themes that respect it
+    /// disable their animations and the layout settles deterministically.
+    /// This requires a Chromium based browser
+    pub fn reduced_motion(mut self, reduced_motion: bool) -> Self {
+        self.reduced_motion = reduced_motion;
+        self
+    }
+
+    /// slow down the CPU of the browser by this factor (e.g. 4 for a 4x
+    /// slowdown) to approximate a weak machine. This requires a Chromium
+    /// based browser
+    pub fn cpu_throttle(mut self, cpu_throttle: Option<f64>) -> Self {
+        self.cpu_throttle = cpu_throttle;
+        self
+    }
+
+    /// emulate a slow network connection, e.g. to catch slides that render
+    /// differently while lazy loaded content is missing. This requires a
+    /// Chromium based browser
+    pub fn network_throttle(
+        mut self,
+        network_throttle: Option<NetworkThrottle>,
+    ) -> Self {
+        self.network_throttle = network_throttle;
+        self
+    }
+
+    /// check that the combination of options is usable before connecting to
+    /// the webdriver
+    fn validate(&self) -> anyhow::Result<()> {
+        // an empty source_dir is the current directory, e.g. the common
+        // directory of the relative source directories a and b
+        let source_dir = if self.source_dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            &self.source_dir
+        };
+        if !source_dir.is_dir() {
+            Err(anyhow!(
+                "source_dir {} is not a directory",
+                self.source_dir.display()
+            ))?;
+        }
+        if self.html_base_url.cannot_be_a_base() {
+            Err(anyhow!(
+                "html_base_url {} cannot be a base url",
+                self.html_base_url
+            ))?;
+        }
+        if self.basic_auth.is_some()
+            && !self.html_base_url.scheme().starts_with("http")
+        {
+            Err(anyhow!("basic authentication requires an http(s) html_base_url"))?;
+        }
+        if self.window_size.0 == 0 || self.window_size.1 == 0 {
+            Err(anyhow!("window size {:?} must not be empty", self.window_size))?;
+        }
+        if self.slide_policy.max_width == 0 || self.slide_policy.max_height == 0 {
+            Err(anyhow!("slide policy max_width and max_height must not be zero"))?;
+        }
+        if self.slide_policy.warn_percent > self.slide_policy.fail_percent {
+            Err(anyhow!(
+                "slide policy warn_percent {} must not exceed fail_percent {}",
+                self.slide_policy.warn_percent,
+                self.slide_policy.fail_percent
+            ))?;
+        }
+        if self.zoom.is_some_and(|zoom| zoom.is_nan() || zoom <= 0.0) {
+            Err(anyhow!("zoom {:?} must be positive", self.zoom))?;
+        }
+        if self.cpu_throttle.is_some_and(|rate| rate.is_nan() || rate < 1.0) {
+            Err(anyhow!("cpu throttle {:?} must be at least 1", self.cpu_throttle))?;
+        }
+        for output_dir in
+            [&self.screenshot_dir, &self.html_dump_dir, &self.dom_dump_dir]
+                .into_iter()
+                .flatten()
+        {
+            if output_dir.is_file() {
+                Err(anyhow!("output directory {} is a file", output_dir.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// validate the options and create the evaluator.
+    /// connects to the webdriver (or Selenium Grid) and requests a new
+    /// session or reuses an existing session.
+    /// fails if the options are invalid or the webclient cannot be created
+    pub async fn build(mut self) -> anyhow::Result<Evaluator<'a>> {
+        self.validate()?;
+        let file_server = if self.serve_source_dir {
+            Some(FileServer::start(&self.source_dir).await?)
+        } else {
+            None
+        };
+        if let Some(file_server) = &file_server {
+            self.html_base_url = file_server.base_url().clone();
+        }
+        if let Some((username, password)) = &self.basic_auth {
+            self.html_base_url
+                .set_username(username)
+                .and_then(|_| self.html_base_url.set_password(Some(password)))
+                .map_err(|_| anyhow!("cannot add credentials to html_base_url"))?;
+        }
+        if self.console_error_policy != ConsoleErrorPolicy::Ignore {
+            if let WebdriverSession::New { capabilities, .. } =
+                &mut self.webdriver_session
+            {
+                let (key, value) = cdp::browser_log_capability();
+                capabilities.insert(key, value);
+            }
+        }
+        let (webclient, owns_session) = self.webdriver_session.connect().await?;
+        if self.navigation_timeout.is_some() || self.find_timeout.is_some() {
+            webclient
+                .update_timeouts(TimeoutConfiguration::new(
+                    self.navigation_timeout,
+                    self.navigation_timeout,
+                    self.find_timeout,
+                ))
+                .await
+                .context("cannot set the timeouts of the webdriver session")?;
+        }
+        // check before the user agent is overridden. the user agent is only
+        // queried if headless is required, the CI warning relies on the
+        // capabilities
+        if self.require_headless {
+            if !is_headless(&webclient).await? {
+                Err(anyhow!("the browser is not running headless"))?;
+            }
+        } else if std::env::var_os("CI").is_some()
+            && headless_capability(&webclient) == Some(false)
+        {
+            warn!(
+                "running a headful browser in CI, window sizes and measurements \
+                 may be inconsistent"
+            );
+        }
+        // use a defined window size for reproducible results
+        if let Err(error) =
+            webclient.set_window_size(self.window_size.0, self.window_size.1).await
+        {
+            if self.require_window_size {
+                return Err(error).context("cannot set the window size");
+            }
+            let (width, height) =
+                webclient.get_window_size().await.unwrap_or_default();
+            warn!(
+                "cannot set the window size to {:?}, keeping {}x{}: {}",
+                self.window_size, width, height, error
+            );
+        }
+        if !self.http_headers.is_empty() {
+            cdp::set_extra_http_headers(&webclient, &self.http_headers).await?;
+        }
+        if let Some(user_agent) = &self.user_agent {
+            cdp::set_user_agent(&webclient, user_agent).await?;
+        }
+        if let Some(locale) = &self.locale {
+            cdp::set_locale(&webclient, locale).await?;
+        }
+        if let Some(timezone) = &self.timezone {
+            cdp::set_timezone(&webclient, timezone).await?;
+        }
+        if self.reduced_motion {
+            cdp::emulate_reduced_motion(&webclient).await?;
+        }
+        if let Some(rate) = self.cpu_throttle {
+            cdp::set_cpu_throttling_rate(&webclient, rate).await?;
+        }
+        if let Some(network_throttle) = self.network_throttle {
+            let (latency, download, upload) = network_throttle.conditions();
+            cdp::emulate_network_conditions(&webclient, latency, download, upload)
+                .await?;
+        }
+        Ok(Evaluator {
+            webclient,
+            owns_session,
+            file_server,
+            element_selector: self.element_selector,
+            screenshot_dir: self.screenshot_dir,
+            screenshot_overlay: self.screenshot_overlay,
+            normalize_dpr: self.normalize_dpr,
+            detect_blank: self.detect_blank,
+            measure_content_box: self.measure_content_box,
+            output_layout: self.output_layout,
+            html_dump_dir: self.html_dump_dir,
+            dom_dump_dir: self.dom_dump_dir,
+            html_base_url: self.html_base_url,
+            source_dir: self.source_dir,
+            cancellation_token: self.cancellation_token,
+            slide_policy: self.slide_policy,
+            max_slides: self.max_slides,
+            max_retries: self.max_retries,
+            console_error_policy: self.console_error_policy,
+            missing_element_policy: self.missing_element_policy,
+            min_element_area: self.min_element_area,
+            frame: self.frame,
+            stability_tolerance: self.stability_tolerance,
+            script_measurement: self.script_measurement,
+            settle_delay: self.settle_delay,
+            reduced_motion: self.reduced_motion,
+            cpu_throttle: self.cpu_throttle,
+            network_throttle: self.network_throttle,
+            zoom: self.zoom,
+            setup_script: self.setup_script,
+            fragment_script: self.fragment_script,
+            extra_css: self.extra_css,
+            fonts: self.fonts,
+            wait_for_ready_state: self.wait_for_ready_state,
+            metrics: self.metrics,
+            incremental_export: self.incremental_export,
+            run_date: current_date(),
+        })
+    }
+}
+
+impl<'a> Evaluator<'a> {
+    /// create a builder for an evaluator that renders the slides in
+    /// source_dir with the given webdriver session
+    pub fn builder(
+        webdriver_session: WebdriverSession,
+        source_dir: PathBuf,
+    ) -> EvaluatorBuilder<'a> {
+        EvaluatorBuilder::new(webdriver_session, source_dir)
+    }
+
+    /// close the webclient session and consume the evaluator. An unclosed
+    /// session cannot be reused by the webdriver. A session that was not
+    /// created by the evaluator is kept open
+    pub async fn close(self) -> anyhow::Result<()> {
+        if !self.owns_session {
+            debug!("keeping webclient session open");
+            return Ok(());
+        }
+        debug!("closing webclient");
+        self.webclient.close().await?;
+        Ok(())
+    }
+
+    /// navigate the webdriver to the given url.
+    /// ensure that html_base_url is set before calling this
+    /// after this call the webdriver will see the content at the url
+    async fn webdriver_open_url(&self, url: &Url) -> Result<(), anyhow::Error> {
+        let mut redacted_url = url.clone();
+        // do not log the basic authentication password
+        let _ = redacted_url.set_password(None);
+        debug!("open url in webclient: {}", redacted_url);
+        self.webclient
+            .goto(url.as_str())
+            .await
+            .with_context(|| format!("cannot open {redacted_url}"))
+    }
+
+    /// switch the webclient into the configured frame of the opened page.
+    /// returns false if the frame does not exist
+    async fn enter_frame(&self, frame: &Frame) -> anyhow::Result<bool> {
+        debug!("enter frame {:?}", frame);
+        let result = match frame {
+            Frame::Index(index) => self.webclient.enter_frame(Some(*index)).await,
+            Frame::Name(name) => {
+                let xpath = format!(r#"//iframe[@name="{name}" or @id="{name}"]"#);
+                match self.webclient.find(fantoccini::Locator::XPath(&xpath)).await {
+                    Result::Ok(iframe) => iframe.enter_frame().await,
+                    Result::Err(error) => Err(error),
+                }
+            }
+        };
+        match result {
+            Result::Ok(()) => Ok(true),
+            Result::Err(fantoccini::error::CmdError::Standard(
+                fantoccini::error::WebDriver {
+                    error:
+                        fantoccini::error::ErrorStatus::NoSuchFrame
+                        | fantoccini::error::ErrorStatus::NoSuchElement,
+                    ..
+                },
+            )) => Ok(false),
+            Result::Err(error) => Err(anyhow!(error))?,
+        }
+    }
+
+    /// the XPath to the evaluated element of the slide. The selector of the
+    /// slide takes precedence over the default selector
+    fn element_selector_of(&self, slide: &Slide) -> Arc<str> {
+        match &slide.element_selector {
+            Some(element_selector) => element_selector.clone(),
+            None => self.element_selector.into(),
+        }
+    }
+
+    /// evaluate the currently opened webpage return the selected content
+    /// element if available. The selector of the slide takes precedence over
+    /// the default selector. If the slide starts at an anchor, the element
+    /// with the anchor id inside the content element is returned
+    async fn get_content_element_from_slide(
+        &self,
+        slide: &Slide,
+    ) -> anyhow::Result<Option<Element>> {
+        let element_selector = self.element_selector_of(slide);
+        self.find_content_element(&element_selector, slide.fragment.as_deref()).await
+    }
+
+    /// return the element with the XPath in the currently opened webpage if
+    /// available. If a fragment is given, the section that starts at the
+    /// element with this id inside it is returned, see
+    /// [FRAGMENT_SECTION_SCRIPT]
+    async fn find_content_element(
+        &self,
+        element_selector: &str,
+        fragment: Option<&str>,
+    ) -> anyhow::Result<Option<Element>> {
+        let content_element =
+            self.webclient.find(fantoccini::Locator::XPath(element_selector)).await;
+        let element = match (content_element, fragment) {
+            (Result::Ok(element), Some(fragment)) => {
+                element.find(fantoccini::Locator::Id(fragment)).await
+            }
+            (result, _) => result,
+        };
+        match element {
+            Result::Ok(anchor) if fragment.is_some() => {
+                Ok(Some(self.fragment_section(anchor).await?))
+            }
+            Result::Ok(result) => Ok(Some(result)),
+            Result::Err(fantoccini::error::CmdError::Standard(
+                fantoccini::error::WebDriver {
+                    error: fantoccini::error::ErrorStatus::NoSuchElement,
+                    ..
+                },
+            )) => anyhow::Ok(None),
+            Result::Err(error) => Err(anyhow!(error))?,
+        }
+    }
+
+    /// the section of the slide that starts at the anchor element of its
+    /// fragment
+    async fn fragment_section(&self, anchor: Element) -> anyhow::Result<Element> {
+        let section = self
+            .webclient
+            .execute(FRAGMENT_SECTION_SCRIPT, vec![serde_json::to_value(&anchor)?])
+            .await?;
+        let element_id = section[WEB_ELEMENT_KEY]
+            .as_str()
+            .ok_or_else(|| anyhow!("invalid fragment section {}", section))?;
+        Ok(Element::from_element_id(
+            self.webclient.clone(),
+            element_id.to_string().into(),
+        ))
+    }
+
+    /// extract the element coordinates from this element
+    async fn get_element_coordinates(
+        &self,
+        element: &Element,
+    ) -> anyhow::Result<ElementSize> {
+        if self.script_measurement {
+            return self.get_element_coordinates_with_script(element).await;
+        }
+        let coordinates = Into::<ElementSize>::into(element.rectangle().await?);
+        Ok(coordinates)
+    }
+
+    /// extract the element coordinates with getBoundingClientRect. The
+    /// position is made relative to the document like the webdriver
+    /// rectangle
+    async fn get_element_coordinates_with_script(
+        &self,
+        element: &Element,
+    ) -> anyhow::Result<ElementSize> {
+        let rect = self
+            .webclient
+            .execute(
+                script!(
+                    "element-rect",
+                    "const rect = arguments[0].getBoundingClientRect();
+                    return [rect.x + window.scrollX, rect.y + window.scrollY,
+                        rect.width, rect.height]"
+                ),
+                vec![serde_json::to_value(element)?],
+            )
+            .await?;
+        let value = |index: usize| {
+            rect[index]
+                .as_f64()
+                .ok_or_else(|| anyhow!("invalid element rectangle {}", rect))
+        };
+        Ok(ElementSize {
+            x: value(0)?,
+            y: value(1)?,
+            width: value(2)?,
+            height: value(3)?,
+        })
+    }
+
+    /// measure everything besides the element size and the custom metrics in
+    /// a single script, so each slide needs one webdriver round trip for
+    /// them. The DOM is only serialized if it is dumped
+    async fn get_page_measurements(
+        &self,
+        element: &Element,
+    ) -> anyhow::Result<PageMeasurements> {
+        let measurements = self
+            .webclient
+            .execute(
+                MEASUREMENT_SCRIPT,
+                vec![
+                    serde_json::to_value(element)?,
+                    self.measure_content_box.into(),
+                    self.dom_dump_dir.is_some().into(),
+                    self.extra_css.is_some().then_some(EXTRA_CSS_ID).into(),
+                ],
+            )
+            .await?;
+        serde_json::from_value(measurements.clone())
+            .with_context(|| format!("invalid measurements {measurements}"))
+    }
+
+    /// take a png screenshot of the element. Drivers clip the screenshot of
+    /// an element that exceeds the viewport or fail to take it. If the
+    /// element screenshot fails, the viewport is captured and cropped to the
+    /// visible part of the element. Returns the screenshot and whether it is
+    /// clipped to the viewport
+    async fn take_screenshot_of_element(
+        &self,
+        element: &Element,
+    ) -> anyhow::Result<(Vec<u8>, bool)> {
+        let (x, y, width, height) = element.rectangle().await?;
+        let viewport = self
+            .webclient
+            .execute(
+                script!(
+                    "viewport",
+                    "return [window.innerWidth, window.innerHeight]"
+                ),
+                vec![],
+            )
+            .await?;
+        let viewport_width = viewport[0].as_f64().unwrap_or_default();
+        let viewport_height = viewport[1].as_f64().unwrap_or_default();
+        let clipped = x < 0.0
+            || y < 0.0
+            || x + width > viewport_width
+            || y + height > viewport_height;
+
+        match element.screenshot().await {
+            Result::Ok(screenshot) => return Ok((screenshot, clipped)),
+            Result::Err(error) => {
+                warn!(
+                    "element screenshot failed, crop the viewport instead: {}",
+                    error
+                )
+            }
+        }
+        let screenshot =
+            image::load_from_memory(&self.webclient.screenshot().await?)?;
+        // the screenshot is in device pixels, the rectangle in css pixels
+        let scale = screenshot.width() as f64 / viewport_width.max(1.0);
+        let left = (x.max(0.0) * scale) as u32;
+        let top = (y.max(0.0) * scale) as u32;
+        let right = ((x + width).min(viewport_width) * scale) as u32;
+        let bottom = ((y + height).min(viewport_height) * scale) as u32;
+        let cropped = screenshot.crop_imm(
+            left,
+            top,
+            right.saturating_sub(left),
+            bottom.saturating_sub(top),
+        );
+        let mut png = io::Cursor::new(vec![]);
+        cropped.write_to(&mut png, image::ImageFormat::Png)?;
+        Ok((png.into_inner(), true))
+    }
+
+    /// scale the png screenshot from device pixels down to css pixels if
+    /// normalize_dpr is set. Returns the screenshot and the number of its
+    /// pixels per css pixel
+    fn normalize_screenshot(
+        &self,
+        screenshot: Vec<u8>,
+        device_pixel_ratio: f64,
+    ) -> anyhow::Result<(Vec<u8>, f64)> {
+        if !self.normalize_dpr || device_pixel_ratio <= 1.0 {
+            return Ok((screenshot, device_pixel_ratio));
+        }
+        Ok((Self::scale_to_css_pixels(&screenshot, device_pixel_ratio)?, 1.0))
+    }
+
+    /// scale the png screenshot down by the device pixel ratio
+    fn scale_to_css_pixels(
+        screenshot: &[u8],
+        device_pixel_ratio: f64,
+    ) -> anyhow::Result<Vec<u8>> {
+        let image = image::load_from_memory(screenshot)?;
+        let width = (image.width() as f64 / device_pixel_ratio).round() as u32;
+        let height = (image.height() as f64 / device_pixel_ratio).round() as u32;
+        let scaled = image.resize_exact(
+            width.max(1),
+            height.max(1),
+            image::imageops::FilterType::Triangle,
+        );
+        let mut png = io::Cursor::new(vec![]);
+        scaled.write_to(&mut png, image::ImageFormat::Png)?;
+        Ok(png.into_inner())
+    }
+
+    /// draw the size limits of the slide policy of the slide as a red
+    /// rectangle starting at the top left corner of the png screenshot. Edges
+    /// beyond the screenshot are left out
+    fn draw_policy_overlay(
+        screenshot: &[u8],
+        slide_policy: &SlidePolicy,
+        scale: f64,
+    ) -> anyhow::Result<Vec<u8>> {
+        const COLOR: image::Rgba<u8> = image::Rgba([255, 0, 0, 255]);
+        const LINE_WIDTH: u32 = 2;
+        // the limits are in css pixels, scale them to the screenshot pixels
+        let mut image = image::load_from_memory(screenshot)?.to_rgba8();
+        let right = (slide_policy.width_limit() as f64 * scale) as u32;
+        let bottom = (slide_policy.height_limit() as f64 * scale) as u32;
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let on_vertical_edge = (x < LINE_WIDTH
+                || (right..right + LINE_WIDTH).contains(&x))
+                && y < bottom + LINE_WIDTH;
+            let on_horizontal_edge = (y < LINE_WIDTH
+                || (bottom..bottom + LINE_WIDTH).contains(&y))
+                && x < right + LINE_WIDTH;
+            if on_vertical_edge || on_horizontal_edge {
+                *pixel = COLOR;
+            }
+        }
+        let mut png = io::Cursor::new(vec![]);
+        image.write_to(&mut png, image::ImageFormat::Png)?;
+        Ok(png.into_inner())
+    }
+
+    /// store the content to a file in output_dir at the relative output path
+    /// of the slide and return the relative path
+    fn store_output_file(
+        &self,
+        output_dir: &Path,
+        content: &[u8],
+        slide: &Slide,
+        extension: &str,
+    ) -> anyhow::Result<PathBuf> {
+        let relative_path = self.output_layout.output_path(
+            &slide.relative_output_path(&self.source_dir, extension)?,
+            &self.run_date,
+        );
+        let output_filename = output_dir.join(&relative_path);
+        debug!("write {} to {}", extension, output_filename.to_str().unwrap());
+
+        // create directories if necessary
+        let output_dir = output_filename.parent().unwrap();
+        if !output_dir.exists() {
+            debug!("creating {}", output_dir.to_str().unwrap());
+            fs::create_dir_all(output_dir).with_context(|| {
+                format!("cannot create directory {}", output_dir.display())
+            })?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&output_filename)
+            .with_context(|| {
+                format!("cannot create {}", output_filename.display())
+            })?;
+
+        file.write_all(content).with_context(|| {
+            format!("cannot write {}", output_filename.display())
+        })?;
+        Ok(relative_path)
+    }
+
+    /// store the screenshot as png and return its path relative to
+    /// screenshot_dir
+    fn store_screenshot(
+        &self,
+        screenshot: Vec<u8>,
+        slide: &Slide,
+    ) -> anyhow::Result<PathBuf> {
+        let screenshot_dir = self.screenshot_dir.as_ref().unwrap();
+        if let Some(max_screenshot_size) = self.slide_policy.max_screenshot_size {
+            if screenshot.len() as u64 > max_screenshot_size {
+                warn!(
+                    "screenshot of {} has {} bytes, more than {} bytes",
+                    slide,
+                    screenshot.len(),
+                    max_screenshot_size
+                );
+            }
+        }
+        self.store_output_file(screenshot_dir, &screenshot, slide, "png")
+    }
+
+    /// store the html that is rendered by the browser for this slide. The
+    /// browser loads the slide file as is, so this is a copy of the file
+    fn store_html(&self, slide: &Slide) -> anyhow::Result<()> {
+        let html_dump_dir = self.html_dump_dir.as_ref().unwrap();
+        let html = slide.read_html()?;
+        self.store_output_file(html_dump_dir, &html, slide, "html")?;
+        Ok(())
+    }
+
+    /// store the live DOM of the opened slide. Inside a frame this is the DOM
+    /// of the frame
+    fn store_dom(&self, slide: &Slide, dom: Option<&str>) -> anyhow::Result<()> {
+        let dom_dump_dir = self.dom_dump_dir.as_ref().unwrap();
+        let dom = dom.ok_or_else(|| anyhow!("cannot read the DOM of {}", slide))?;
+        self.store_output_file(dom_dump_dir, dom.as_bytes(), slide, "html")?;
+        Ok(())
+    }
+
+    /// the url of the slide file. The local file server serves the files
+    /// relative to source_dir, otherwise the path of the file is joined to
+    /// the html_base_url
+    fn slide_url(&self, slide: &Slide) -> anyhow::Result<Url> {
+        let path = match self.file_server {
+            Some(_) => slide.filename.strip_prefix(&self.source_dir)?,
+            None => &slide.filename,
+        };
+        join_url_path(&self.html_base_url, path)
+    }
+
+    /// the browser cannot render a gzip compressed slide directly. Open the
+    /// directory of the slide instead, so relative links resolve as for the
+    /// uncompressed page, and replace the document with the decompressed html
+    async fn open_gzipped_slide(&self, slide: &Slide) -> anyhow::Result<()> {
+        let url = self.slide_url(slide)?;
+        self.webdriver_open_url(&url.join(".")?).await?;
+        let html = slide.read_html_text()?;
+        self.webclient
+            .execute(
+                script!(
+                    "write-document",
+                    "document.open(); document.write(arguments[0]); document.close();"
+                ),
+                vec![html.into()],
+            )
+            .await?;
+        if let Some(fragment) = &slide.fragment {
+            self.webclient
+                .execute(
+                    script!("set-fragment", "location.hash = arguments[0]"),
+                    vec![fragment.clone().into()],
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// open the slide in the webclient and return its content element if
+    /// available
+    async fn open_slide(&self, slide: &Slide) -> anyhow::Result<Option<Element>> {
+        let navigation = async {
+            if slide.is_gzipped() {
+                self.open_gzipped_slide(slide).await
+            } else {
+                let mut url = self.slide_url(slide)?;
+                url.set_fragment(slide.fragment.as_deref());
+                self.webdriver_open_url(&url).await
+            }
+        };
+        navigation.await.context(NavigationFailure)?;
+        if self.wait_for_ready_state {
+            self.wait_until_complete(slide).await?;
+        }
+        if !self.fonts.is_empty() {
+            self.load_fonts(slide).await?;
+        }
+        if let Some(extra_css) = &self.extra_css {
+            self.webclient
+                .execute(
+                    script!(
+                        "add-extra-css",
+                        "const style = document.createElement('style');
+                        style.id = arguments[1];
+                        style.textContent = arguments[0];
+                        document.head.appendChild(style);
+                        style.dataset.rules = style.sheet.cssRules.length;"
+                    ),
+                    vec![extra_css.clone().into(), EXTRA_CSS_ID.into()],
+                )
+                .await?;
+        }
+        if let Some(zoom) = self.zoom {
+            // css zoom changes the layout and the reported element sizes
+            // like the zoom of the browser
+            self.webclient
+                .execute(
+                    script!(
+                        "zoom",
+                        "document.documentElement.style.zoom = arguments[0]"
+                    ),
+                    vec![zoom.into()],
+                )
+                .await?;
+        }
+        if let Some(setup_script) = &self.setup_script {
+            debug!("execute setup script in {}", slide);
+            self.webclient.execute(setup_script, vec![]).await?;
+        }
+        if let Some(settle_delay) = self.settle_delay {
+            debug!("wait {:?} for {} to settle", settle_delay, slide);
+            tokio::time::sleep(settle_delay).await;
+        }
+        if let Some(frame) = &self.frame {
+            if !self.enter_frame(frame).await? {
+                warn!("frame {:?} not found in {}", frame, slide);
+                return Ok(None);
+            }
+        }
+        self.get_content_element_from_slide(slide).await
+    }
+
+    /// add the @font-face rules of the fonts to the opened slide and wait
+    /// until the browser loaded them
+    async fn load_fonts(&self, slide: &Slide) -> anyhow::Result<()> {
+        let families: Vec<&str> =
+            self.fonts.iter().map(|font| font.family.as_str()).collect();
+        let failed_families = self
+            .webclient
+            .execute_async(
+                script!(
+                    "load-fonts",
+                    "const [css, families, done] = arguments;
+                    const style = document.createElement('style');
+                    style.textContent = css;
+                    document.head.appendChild(style);
+                    Promise.all(families.map((family) =>
+                        document.fonts.load('16px ' + JSON.stringify(family))
+                            .then((faces) => faces.length > 0, () => false)))
+                        .then((loaded) => done(families.filter((_, i) => !loaded[i])));"
+                ),
+                vec![
+                    font_face_css(&self.fonts).into(),
+                    serde_json::to_value(families)?,
+                ],
+            )
+            .await?;
+        for family in failed_families.as_array().into_iter().flatten() {
+            warn!("font {} is not loaded in {}", family, slide);
+        }
+        Ok(())
+    }
+
+    /// poll the document ready state of the opened slide until it is
+    /// complete. Gives up with a warning after READY_STATE_TIMEOUT
+    async fn wait_until_complete(&self, slide: &Slide) -> anyhow::Result<()> {
+        const READY_STATE_TIMEOUT: Duration = Duration::from_secs(10);
+        const POLL_INTERVAL: Duration = Duration::from_millis(50);
+        let start = Instant::now();
+        loop {
+            let ready_state = self
+                .webclient
+                .execute(
+                    script!("ready-state", "return document.readyState"),
+                    vec![],
+                )
+                .await?;
+            if ready_state == "complete" {
+                return Ok(());
+            }
+            if start.elapsed() >= READY_STATE_TIMEOUT {
+                warn!(
+                    "{} is not completely loaded after {:?}, measure anyway",
+                    slide, READY_STATE_TIMEOUT
+                );
+                return Ok(());
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// measure the size of the opened content element. With a fragment
+    /// script the size is the largest across all reveal steps
+    async fn measure_element_size(
+        &self,
+        slide: &Slide,
+        content_element: &Element,
+    ) -> anyhow::Result<(ElementSize, usize)> {
+        let element_size = self.get_element_coordinates(content_element).await?;
+        match &self.fragment_script {
+            Some(fragment_script) => {
+                self.eval_fragments(
+                    slide,
+                    content_element,
+                    element_size,
+                    fragment_script,
+                )
+                .await
+            }
+            None => Ok((element_size, 0)),
+        }
+    }
+
+    /// open the slide a second time, repeat the measurement including the
+    /// fragment steps and check if the element size differs by more than the
+    /// tolerance from the first measurement
+    async fn eval_stability(
+        &self,
+        slide: &Slide,
+        element_size: &ElementSize,
+        tolerance: f64,
+    ) -> anyhow::Result<bool> {
+        let Some(content_element) = self.open_slide(slide).await? else {
+            warn!("content element disappeared on second measurement: {}", slide);
+            return Ok(false);
+        };
+        let (second_size, _) =
+            self.measure_element_size(slide, &content_element).await?;
+        let stable = (second_size.width - element_size.width).abs() <= tolerance
+            && (second_size.height - element_size.height).abs() <= tolerance;
+        if !stable {
+            warn!(
+                "unstable measurement of {}: {:?} vs {:?}",
+                slide, element_size, second_size
+            );
+        }
+        Ok(stable)
+    }
+
+    /// reveal the fragments of the opened slide one by one with the fragment
+    /// script and measure the element after each step. Returns the largest
+    /// width and height across all steps and the number of steps
+    async fn eval_fragments(
+        &self,
+        slide: &Slide,
+        content_element: &Element,
+        mut element_size: ElementSize,
+        fragment_script: &str,
+    ) -> anyhow::Result<(ElementSize, usize)> {
+        const MAX_FRAGMENT_STEPS: usize = 100;
+        for steps in 0..MAX_FRAGMENT_STEPS {
+            let revealed = self.webclient.execute(fragment_script, vec![]).await?;
+            if revealed.as_bool() != Some(true) {
+                return Ok((element_size, steps));
+            }
+            if let Some(settle_delay) = self.settle_delay {
+                tokio::time::sleep(settle_delay).await;
+            }
+            let step_size = self.get_element_coordinates(content_element).await?;
+            debug!("fragment {} of {}: {:?}", steps + 1, slide, step_size);
+            element_size.width = element_size.width.max(step_size.width);
+            element_size.height = element_size.height.max(step_size.height);
+        }
+        warn!(
+            "{} still reveals fragments after {} steps, stop stepping",
+            slide, MAX_FRAGMENT_STEPS
+        );
+        Ok((element_size, MAX_FRAGMENT_STEPS))
+    }
+
+    /// render the html content as a data uri and measure the element with
+    /// the default XPath, e.g. to check that a slide template fits without
+    /// writing it to a file. Relative links in the html cannot be resolved.
+    /// Returns none if the element is not found
+    pub async fn eval_html(
+        &self,
+        html: &str,
+    ) -> anyhow::Result<Option<ElementSize>> {
+        let encoded_html = base64::engine::general_purpose::STANDARD.encode(html);
+        // the url is not logged as it contains the whole html
+        debug!("open html string in webclient");
+        let url = format!("data:text/html;base64,{encoded_html}");
+        self.webclient.goto(&url).await.context("cannot open the html string")?;
+        let Some(content_element) =
+            self.find_content_element(self.element_selector, None).await?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(self.get_element_coordinates(&content_element).await?))
+    }
+
+    /// evaluate a single slide
+    pub async fn eval_slide(
+        &self,
+        slide: &Slide,
+    ) -> anyhow::Result<Option<EvaluationResult>> {
+        debug!("evaluating {:?}", slide);
+
+        if self.html_dump_dir.is_some() {
+            self.store_html(slide)?;
+        }
+        if self.console_error_policy != ConsoleErrorPolicy::Ignore {
+            // discard errors of previously opened pages
+            cdp::take_console_errors(&self.webclient).await?;
+        }
+        let Some(content_element) = self.open_slide(slide).await? else {
+            return Ok(None);
+        };
+        let (element_size, fragment_steps) =
+            self.measure_element_size(slide, &content_element).await?;
+        if element_size.width * element_size.height < self.min_element_area {
+            debug!("element of {} is too small: {:?}", slide, element_size);
+            return Ok(None);
+        }
+        let measurements = self.get_page_measurements(&content_element).await?;
+        if self.dom_dump_dir.is_some() {
+            self.store_dom(slide, measurements.dom.as_deref())?;
+        }
+        let render_time = measurements.render_time.map(|render_time| {
+            Duration::from_secs_f64(render_time.max(0.0) / 1000.0)
+        });
+        let clipped_elements = measurements.clipped_elements;
+        if clipped_elements > 0 {
+            warn!("{} elements with clipped text in {}", clipped_elements, slide);
+        }
+        let metrics =
+            self.metrics.measure_all(&self.webclient, &content_element).await?;
+        let max_height_override = self.slide_policy.max_height_for_classes(
+            measurements.class_attribute.as_deref().unwrap_or_default(),
+        );
+        if let Some(max_height) = max_height_override {
+            debug!("maximum height of {} is {}", slide, max_height);
+        }
+        let slide_policy = self.slide_policy.with_max_height(max_height_override);
+        let (screenshot, screenshot_size, screenshot_clipped, blank) =
+            if self.screenshot_dir.is_some() || self.detect_blank {
+                let (screenshot, clipped) =
+                    self.take_screenshot_of_element(&content_element).await?;
+                let (mut screenshot, screenshot_scale) = self.normalize_screenshot(
+                    screenshot,
+                    measurements.device_pixel_ratio,
+                )?;
+                // check before the overlay adds another color
+                let blank = self.detect_blank && is_blank(&screenshot)?;
+                if self.screenshot_overlay {
+                    screenshot = Self::draw_policy_overlay(
+                        &screenshot,
+                        &slide_policy,
+                        screenshot_scale,
+                    )?;
+                }
+                let (screenshot, screenshot_size) = match self.screenshot_dir {
+                    Some(_) => {
+                        let screenshot_size = screenshot.len() as u64;
+                        let screenshot = self.store_screenshot(screenshot, slide)?;
+                        (Some(screenshot), Some(screenshot_size))
+                    }
+                    None => (None, None),
+                };
+                (screenshot, screenshot_size, clipped, blank)
+            } else {
+                (None, None, false, false)
+            };
+        if blank {
+            warn!("slide renders blank: {}", slide);
+        }
+        let extra_css_applied = measurements.extra_css_applied;
+        if extra_css_applied == Some(false) {
+            warn!("extra css is not completely applied to {}", slide);
+        }
+        let console_errors = match self.console_error_policy {
+            ConsoleErrorPolicy::Ignore => vec![],
+            _ => cdp::take_console_errors(&self.webclient).await?,
+        };
+        let unstable = match self.stability_tolerance {
+            Some(tolerance) => {
+                !self.eval_stability(slide, &element_size, tolerance).await?
+            }
+            None => false,
+        };
+        let overflow = slide_policy.eval_overflow(&element_size);
+        let mut policy_violations = slide_policy.eval_size(&element_size);
+        if self.console_error_policy == ConsoleErrorPolicy::Fail
+            && !console_errors.is_empty()
+        {
+            policy_violations.push(PolicyViolation::ConsoleErrors);
+        }
+        policy_violations.extend(slide_policy.eval_render_time(render_time));
+        if let Some(screenshot_size) = screenshot_size {
+            policy_violations
+                .extend(slide_policy.eval_screenshot_size(screenshot_size));
+        }
+        if slide_policy.fail_on_clipped_text && clipped_elements > 0 {
+            policy_violations.push(PolicyViolation::ClippedText);
+        }
+        let fill_ratio = slide_policy.eval_fill_ratio(&element_size);
+        let severity = slide_policy.eval_severity(fill_ratio, &policy_violations);
+        let result = EvaluationResult {
+            slide: slide.clone(),
+            element_size,
+            page_scroll_height: measurements.page_scroll_height,
+            device_pixel_ratio: measurements.device_pixel_ratio,
+            root_font_size: measurements.root_font_size,
+            render_time,
+            max_code_lines: measurements.max_code_lines,
+            clipped_elements,
+            fragment_steps,
+            max_height_override,
+            content_box: measurements.content_box,
+            metrics,
+            overflow,
+            screenshot,
+            screenshot_clipped,
+            console_errors,
+            policy_violations,
+            fill_ratio,
+            severity,
+            unstable,
+            blank,
+            extra_css_applied,
+            retries: 0,
+            estimated: false,
+            element_selector: Some(self.element_selector_of(slide)),
+        };
+        debug!("information about element: {:?}", result);
+        Ok(Some(result))
+    }
+
+    /// an empty measurement for a slide without the evaluated element
+    fn empty_result(&self, slide: &Slide) -> EvaluationResult {
+        let element_size = ElementSize { x: 0.0, y: 0.0, width: 0.0, height: 0.0 };
+        EvaluationResult {
+            slide: slide.clone(),
+            overflow: self.slide_policy.eval_overflow(&element_size),
+            element_size,
+            page_scroll_height: 0.0,
+            device_pixel_ratio: 1.0,
+            root_font_size: 16.0,
+            render_time: None,
+            max_code_lines: 0,
+            clipped_elements: 0,
+            fragment_steps: 0,
+            max_height_override: None,
+            content_box: None,
+            metrics: vec![],
+            screenshot: None,
+            screenshot_clipped: false,
+            console_errors: vec![],
+            policy_violations: vec![],
+            fill_ratio: 0.0,
+            severity: Severity::Pass,
+            unstable: false,
+            blank: false,
+            extra_css_applied: None,
+            retries: 0,
+            estimated: false,
+            element_selector: None,
+        }
+    }
+
+    /// evaluate a single slide and retry up to max_retries times if the
+    /// evaluation fails. Returns the result and the number of retries
+    async fn eval_slide_with_retries(
+        &self,
+        slide: &Slide,
+    ) -> anyhow::Result<(Option<EvaluationResult>, usize)> {
+        let mut retries = 0;
+        loop {
+            match self.eval_slide(slide).await {
+                Result::Ok(result) => return Ok((result, retries)),
+                Result::Err(error) if retries < self.max_retries => {
+                    retries += 1;
+                    warn!(
+                        "evaluation of {} failed, retry {}: {}",
+                        slide, retries, error
+                    );
+                }
+                Result::Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// take a screenshot of the content element of each slide without
+    /// measuring it, e.g. for a visual review. Requires a screenshot
+    /// directory. Returns the number of stored screenshots
+    pub async fn screenshot_book(&self, book: &Book) -> anyhow::Result<usize> {
+        if self.screenshot_dir.is_none() {
+            Err(anyhow!("taking screenshots requires a screenshot directory"))?;
+        }
+        let mut screenshots = 0;
+        let max_slides = self.max_slides.unwrap_or(usize::MAX);
+        for slide in book.slides().iter().take(max_slides) {
+            if self.cancellation_token.is_cancelled() {
+                debug!("received cancel request, stop taking screenshots");
+                break;
+            }
+            let Some(content_element) = self.open_slide(slide).await? else {
+                warn!("slide with no content - no screenshot: {:?}", slide);
+                continue;
+            };
+            let (screenshot, _) =
+                self.take_screenshot_of_element(&content_element).await?;
+            let measurements = self.get_page_measurements(&content_element).await?;
+            let (mut screenshot, screenshot_scale) = self
+                .normalize_screenshot(screenshot, measurements.device_pixel_ratio)?;
+            if self.screenshot_overlay {
+                let slide_policy = self.slide_policy.with_max_height(
+                    self.slide_policy.max_height_for_classes(
+                        measurements.class_attribute.as_deref().unwrap_or_default(),
+                    ),
+                );
+                screenshot = Self::draw_policy_overlay(
+                    &screenshot,
+                    &slide_policy,
+                    screenshot_scale,
+                )?;
+            }
+            self.store_screenshot(screenshot, slide)?;
+            screenshots += 1;
+        }
+        Ok(screenshots)
+    }
+
+    /// evaluate an entire book
+    pub async fn eval_book(&self, book: Book) -> anyhow::Result<EvaluationResults> {
+        debug!("slide count: {}", book.slides().len());
+        if let Some(settle_delay) = self.settle_delay {
+            info!("measuring each slide {:?} after opening it", settle_delay);
+        }
+        let max_slides = self.max_slides.unwrap_or(usize::MAX);
+        let slides: Vec<Slide> =
+            book.slides().iter().take(max_slides).cloned().collect();
+        let mut results = EvaluationResults {
+            book,
+            results: vec![],
+            skipped: vec![],
+            label: None,
+            settle_delay: self.settle_delay,
+            reduced_motion: self.reduced_motion,
+            cpu_throttle: self.cpu_throttle,
+            network_throttle: self.network_throttle,
+            warnings_as_errors: false,
+            unit: Unit::Px,
+            slide_policy: self.slide_policy.clone(),
+            height_threshold: None,
+        };
+        let mut incremental_export = match &self.incremental_export {
+            Some(file) => Some(
+                fs::File::create(file)
+                    .with_context(|| format!("cannot create {}", file.display()))?,
+            ),
+            None => None,
+        };
+        for slide in &slides {
+            if self.cancellation_token.is_cancelled() {
+                debug!("received cancel request, return already completed results");
+                break;
+            }
+            let (result, retries) = match self.eval_slide_with_retries(slide).await {
+                Result::Ok(evaluation) => evaluation,
+                Result::Err(error)
+                    if error.downcast_ref::<NavigationFailure>().is_some() =>
+                {
+                    warn!("cannot open {} - ignore: {:#}", slide, error);
+                    results.skipped.push(SkippedSlide {
+                        slide: slide.clone(),
+                        reason: SkipReason::NavigationFailed,
+                        error: Some(format!("{error:#}")),
+                    });
+                    continue;
+                }
+                Result::Err(error) => return Err(error),
+            };
+            let mut result = match (result, self.missing_element_policy) {
+                (Some(result), _) => result,
+                (None, MissingElementPolicy::Skip) => {
+                    warn!("slide with no content - ignore: {:?}", slide);
+                    results.skipped.push(SkippedSlide {
+                        slide: slide.clone(),
+                        reason: SkipReason::NoContent,
+                        error: None,
+                    });
+                    continue;
+                }
+                (None, MissingElementPolicy::Fail) => {
+                    Err(anyhow!("content element not found in {}", slide))?
+                }
+                (None, MissingElementPolicy::Empty) => {
+                    warn!(
+                        "slide with no content - record empty result: {:?}",
+                        slide
+                    );
+                    self.empty_result(slide)
+                }
+            };
+            result.retries = retries;
+            if let Some(file) = &mut incremental_export {
+                // a single write per record, so a crash leaves complete lines
+                let mut line = serde_json::to_vec(&results.export_record(
+                    &result,
+                    RoundingMode::None,
+                    false,
+                ))?;
+                line.push(b'\n');
+                file.write_all(&line)?;
+            }
+            results.results.push(result);
+        }
+        Ok(results)
+    }
+}
+
+/// all possible policy violations
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize)]
+enum PolicyViolation {
+    /// violation of the maximum width (horizontal overflow)
+    MaxWidth,
+    /// violation of the maximum height (vertical overflow)
+    MaxHeight,
+    /// the browser logged errors while rendering the slide
+    ConsoleErrors,
+    /// the slide took longer to render than the maximum render time
+    RenderTime,
+    /// the screenshot of the slide is larger than the maximum size
+    ScreenshotSize,
+    /// text in the slide is cut off
+    ClippedText,
+    /// the slide is higher than the height threshold derived from the
+    /// heights of all slides of the book
+    HeightOutlier,
+    /// the bottom of the element is below the fold
+    BelowFold,
+}
+
+impl PolicyViolation {
+    /// true for the violations of the size of the element, see
+    /// [SlidePolicy::eval_size]
+    fn is_size(&self) -> bool {
+        matches!(
+            self,
+            PolicyViolation::MaxWidth
+                | PolicyViolation::MaxHeight
+                | PolicyViolation::HeightOutlier
+                | PolicyViolation::BelowFold
+        )
+    }
+
+    /// the phrase of the failure banner for one and for several slides with
+    /// the violation
+    fn banner_phrase(&self) -> (&'static str, &'static str) {
+        match self {
+            PolicyViolation::MaxWidth => {
+                ("overflows horizontally", "overflow horizontally")
+            }
+            PolicyViolation::MaxHeight => {
+                ("overflows vertically", "overflow vertically")
+            }
+            PolicyViolation::ConsoleErrors => {
+                ("logs console errors", "log console errors")
+            }
+            PolicyViolation::RenderTime => {
+                ("renders too slowly", "render too slowly")
+            }
+            PolicyViolation::ScreenshotSize => {
+                ("has a too large screenshot", "have too large screenshots")
+            }
+            PolicyViolation::ClippedText => ("cuts off text", "cut off text"),
+            PolicyViolation::HeightOutlier => (
+                "is taller than the rest of the book",
+                "are taller than the rest of the book",
+            ),
+            PolicyViolation::BelowFold => {
+                ("extends below the fold", "extend below the fold")
+            }
+        }
+    }
+}
+
+/// the SlidePolicy struct contains all parameters for evaluating a slide
+#[derive(Clone, Serialize)]
+pub struct SlidePolicy {
+    /// the maximum allowed width of a slide
+    pub max_width: usize,
+    /// the maximum allowed height of a slide
+    pub max_height: usize,
+    /// slides that fill at least this percentage of the maximum size warn
+    pub warn_percent: usize,
+    /// slides that fill more than this percentage of the maximum size fail
+    pub fail_percent: usize,
+    /// the message reported for failing slides if provided. The
+    /// placeholders {path}, {width}, {height}, {max_width} and {max_height}
+    /// are replaced with the values of the slide in the unit and rounding
+    /// of the export
+    pub failure_message: Option<String>,
+    /// slides that take longer to load and render fail if provided
+    #[serde(serialize_with = "serialize_optional_duration")]
+    pub max_render_time: Option<Duration>,
+    /// slides whose screenshot has more bytes fail if provided. A large
+    /// screenshot usually means a very tall slide
+    pub max_screenshot_size: Option<u64>,
+    /// slides with elements whose text is cut off fail
+    pub fail_on_clipped_text: bool,
+    /// the maximum height of slides whose evaluated element has the class,
+    /// e.g. slide-eval-tall for slides that are known to be long. The first
+    /// class of the element with a mapping applies
+    pub class_max_heights: Vec<(String, usize)>,
+    /// slides whose element ends below this y coordinate of the page fail if
+    /// provided, e.g. the bottom of the area visible on a projector. Unlike
+    /// the maximum height this includes the space above the element
+    pub fold: Option<usize>,
+}
+
+impl Default for SlidePolicy {
+    /// the default height/width values have a 16/9 ratio
+    fn default() -> Self {
+        SlidePolicy {
+            max_width: 750,
+            max_height: 1333,
+            warn_percent: 90,
+            fail_percent: 100,
+            failure_message: None,
+            max_render_time: None,
+            max_screenshot_size: None,
+            fail_on_clipped_text: false,
+            class_max_heights: vec![],
+            fold: None,
+        }
+    }
+}
+
+impl SlidePolicy {
+    /// the policy with a different maximum height if provided, e.g. the
+    /// maximum height mapped to a class of the element
+    fn with_max_height(&self, max_height: Option<usize>) -> Cow<'_, SlidePolicy> {
+        match max_height {
+            Some(max_height) => {
+                Cow::Owned(SlidePolicy { max_height, ..self.clone() })
+            }
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    /// the maximum height mapped to the first class in the space separated
+    /// class list that has a mapping. None if no class is mapped
+    fn max_height_for_classes(&self, classes: &str) -> Option<usize> {
+        classes.split_whitespace().find_map(|class| {
+            self.class_max_heights
+                .iter()
+                .find(|(mapped_class, _)| mapped_class == class)
+                .map(|(_, max_height)| *max_height)
+        })
+    }
+
+    /// the width above which a slide fails
+    fn width_limit(&self) -> usize {
+        self.max_width * self.fail_percent / 100
+    }
+
+    /// the height above which a slide fails
+    fn height_limit(&self) -> usize {
+        self.max_height * self.fail_percent / 100
+    }
+
+    /// evaluate if the width is within the policy
+    fn eval_width(&self, element_size: &ElementSize) -> Option<PolicyViolation> {
+        if element_size.width as usize > self.width_limit() {
+            return Some(PolicyViolation::MaxWidth);
+        }
+        None
+    }
+
+    /// evaluate if the height is within the policy
+    fn eval_height(&self, element_size: &ElementSize) -> Option<PolicyViolation> {
+        if element_size.height as usize > self.height_limit() {
+            return Some(PolicyViolation::MaxHeight);
+        }
+        None
+    }
+
+    /// the horizontal and vertical overflow of the element
+    fn eval_overflow(&self, element_size: &ElementSize) -> Overflow {
+        Overflow {
+            horizontal: (element_size.width - self.width_limit() as f64).max(0.0),
+            vertical: (element_size.height - self.height_limit() as f64).max(0.0),
+        }
+    }
+
+    /// the ratio of the element size to the maximum size in the direction
+    /// that is filled the most
+    fn eval_fill_ratio(&self, element_size: &ElementSize) -> f64 {
+        f64::max(
+            element_size.width / self.max_width as f64,
+            element_size.height / self.max_height as f64,
+        )
+    }
+
+    /// slides with policy violations fail, slides that fill at least
+    /// warn_percent of the maximum size warn
+    fn eval_severity(
+        &self,
+        fill_ratio: f64,
+        policy_violations: &[PolicyViolation],
+    ) -> Severity {
+        if !policy_violations.is_empty() {
+            Severity::Fail
+        } else if fill_ratio * 100.0 >= self.warn_percent as f64 {
+            Severity::Warn
+        } else {
+            Severity::Pass
+        }
+    }
+
+    /// the failure message for a failing slide if a template is provided.
+    /// The dimensions are formatted after converting them with dimension,
+    /// e.g. to round them like the exported dimensions
+    fn eval_failure_message(
+        &self,
+        slide: &Slide,
+        element_size: &ElementSize,
+        severity: Severity,
+        dimension: impl Fn(f64) -> f64,
+    ) -> Option<String> {
+        if severity != Severity::Fail {
+            return None;
+        }
+        let template = self.failure_message.as_ref()?;
+        let dimension = |value: f64| dimension(value).to_string();
+        Some(
+            template
+                .replace("{path}", &slide.to_string())
+                .replace("{width}", &dimension(element_size.width))
+                .replace("{height}", &dimension(element_size.height))
+                .replace("{max_width}", &dimension(self.max_width as f64))
+                .replace("{max_height}", &dimension(self.max_height as f64)),
+        )
+    }
+
+    /// evaluate if the render time is within the policy. An unavailable
+    /// render time is not a violation
+    fn eval_render_time(
+        &self,
+        render_time: Option<Duration>,
+    ) -> Option<PolicyViolation> {
+        match (self.max_render_time, render_time) {
+            (Some(max_render_time), Some(render_time))
+                if render_time > max_render_time =>
+            {
+                Some(PolicyViolation::RenderTime)
+            }
+            _ => None,
+        }
+    }
+
+    /// evaluate if the size of the screenshot in bytes is within the policy
+    fn eval_screenshot_size(&self, screenshot_size: u64) -> Option<PolicyViolation> {
+        match self.max_screenshot_size {
+            Some(max_screenshot_size) if screenshot_size > max_screenshot_size => {
+                Some(PolicyViolation::ScreenshotSize)
+            }
+            _ => None,
+        }
+    }
+
+    /// evaluate if the bottom of the element is above the fold
+    fn eval_fold(&self, element_size: &ElementSize) -> Option<PolicyViolation> {
+        match self.fold {
+            Some(fold) if element_size.y + element_size.height > fold as f64 => {
+                Some(PolicyViolation::BelowFold)
+            }
+            _ => None,
+        }
+    }
+
+    /// evaluate all size policies
+    fn eval_size(&self, element_size: &ElementSize) -> Vec<PolicyViolation> {
+        [
+            self.eval_height(element_size),
+            self.eval_width(element_size),
+            self.eval_fold(element_size),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sql_export_can_be_imported_again() {
+        let book_dir = tempfile::tempdir().unwrap();
+        for page in ["a.html", "b.html", "unreadable.html"] {
+            fs::write(book_dir.path().join(page), "<main><p>same</p></main>")
+                .unwrap();
+        }
+        let book = Book::from_html_slides(book_dir.path().to_path_buf()).unwrap();
+        let mut results = EvaluationResults::estimate(
+            book,
+            "//main",
+            None,
+            &SlidePolicy::default(),
+            &LayoutMetrics::default(),
+        )
+        .unwrap();
+        // the same content has the same slide id, a page that could not be
+        // read when the book was discovered has none
+        assert_eq!(results.results[0].slide.id(), results.results[1].slide.id());
+        results.results[2].slide.content_hash = None;
+        let sql_file = book_dir.path().join("run.sql");
+        results.export_sql(&sql_file, false, "run-1", SystemTime::now()).unwrap();
+
+        let database = book_dir.path().join("history.db");
+        let import = || {
+            std::process::Command::new("sqlite3")
+                .arg(&database)
+                .stdin(fs::File::open(&sql_file).unwrap())
+                .output()
+        };
+        match import() {
+            Ok(output) => assert!(output.status.success(), "{output:?}"),
+            // the script can only be imported where sqlite3 is installed
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return,
+            Err(error) => panic!("cannot run sqlite3: {error}"),
+        }
+        assert!(import().unwrap().status.success());
+        let output = std::process::Command::new("sqlite3")
+            .arg(&database)
+            .arg("SELECT filename, slide_id IS NULL FROM slide_results ORDER BY filename")
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "a.html|0\nb.html|0\nunreadable.html|1\n"
+        );
+    }
+
+    /// run the javascript with node and return its output. None if node is
+    /// not installed, the scripts can only be checked where it is
+    fn run_node(javascript: &str, check_only: bool) -> Option<std::process::Output> {
+        let script_dir = tempfile::tempdir().unwrap();
+        let script_file = script_dir.path().join("script.js");
+        fs::write(&script_file, javascript).unwrap();
+        let mut command = std::process::Command::new("node");
+        if check_only {
+            command.arg("--check");
+        }
+        match command.arg(&script_file).output() {
+            Ok(output) => {
+                assert!(
+                    output.status.success(),
+                    "{}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                Some(output)
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => panic!("cannot run node: {error}"),
+        }
+    }
+
+    #[test]
+    fn scripts_parse() {
+        // webdriver runs the scripts as the body of a function
+        for script in [MEASUREMENT_SCRIPT, FRAGMENT_SECTION_SCRIPT] {
+            run_node(&format!("(function () {{\n{script}\n}});\n"), true);
+        }
+    }
+
+    #[test]
+    fn fragment_section_of_a_heading_anchor() {
+        // just enough of the DOM for the script
+        let dom = r"class Node {
+    constructor(tagName) {
+        this.tagName = tagName;
+        this.parentElement = null;
+        this.children = [];
+        this.dataset = {};
+        this.style = {};
+    }
+    get nextSibling() {
+        const siblings = this.parentElement.children;
+        return siblings[siblings.indexOf(this) + 1] || null;
+    }
+    closest(selectors) {
+        const tagNames = selectors.toUpperCase().split(', ');
+        let element = this;
+        while (element && !tagNames.includes(element.tagName)) {
+            element = element.parentElement;
+        }
+        return element;
+    }
+    before(node) {
+        const siblings = this.parentElement.children;
+        siblings.splice(siblings.indexOf(this), 0, node);
+        node.parentElement = this.parentElement;
+    }
+    append(node) {
+        const siblings = node.parentElement.children;
+        siblings.splice(siblings.indexOf(node), 1);
+        this.children.push(node);
+        node.parentElement = this;
+    }
+}
+const document = { createElement: (tagName) => new Node(tagName.toUpperCase()) };
+function element(tagName, ...children) {
+    const element = new Node(tagName);
+    for (const child of children) {
+        child.parentElement = element;
+        element.children.push(child);
+    }
+    return element;
+}
+const tagNames = (element) => element.children.map((child) => child.tagName || '#text');
+";
+        let test = r"const anchor = element('A');
+const heading = element('H2', anchor);
+const other = element('SECTION');
+const main = element('MAIN', element('H1'), heading, element('P'), element('H3'),
+    element('PRE'), new Node(), element('H2'), other);
+const section = fragmentSection(anchor);
+console.log(JSON.stringify([
+    tagNames(main),
+    tagNames(section),
+    section.style.display,
+    fragmentSection(heading) === section,
+    fragmentSection(other) === other,
+]));
+";
+        let javascript = format!(
+            "{dom}function fragmentSection() {{\n{FRAGMENT_SECTION_SCRIPT}\n}}\n{test}"
+        );
+        let Some(output) = run_node(&javascript, false) else {
+            return;
+        };
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            r##"[["H1","DIV","H2","SECTION"],["H2","P","H3","PRE","#text"],"flow-root",true,true]"##
+        );
+    }
+
+    #[test]
+    fn max_size_is_compared_with_whole_pixels() {
+        let slide_policy = SlidePolicy::default();
+        let size = |width| ElementSize { x: 0.0, y: 0.0, width, height: 100.0 };
+        // exported as 751 when rounded, but fits
+        assert!(slide_policy.eval_size(&size(750.6)).is_empty());
+        assert_eq!(
+            slide_policy.eval_size(&size(751.0)),
+            vec![PolicyViolation::MaxWidth]
+        );
+        let slide_policy = SlidePolicy { fold: Some(100), ..SlidePolicy::default() };
+        // the element ends at the fold
+        assert!(slide_policy.eval_size(&size(10.0)).is_empty());
+        let below_fold = ElementSize { y: 0.4, ..size(10.0) };
+        assert_eq!(
+            slide_policy.eval_size(&below_fold),
+            vec![PolicyViolation::BelowFold]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_invalid_options() {
+        let builder = || {
+            let webdriver_session = WebdriverSession::New {
+                webdriver: String::from("http://localhost:4444"),
+                capabilities: Capabilities::new(),
+            };
+            Evaluator::builder(webdriver_session, PathBuf::from("."))
+        };
+        builder().validate().unwrap();
+        let error =
+            |builder: EvaluatorBuilder| builder.validate().unwrap_err().to_string();
+        assert_eq!(
+            error(builder().window_size(0, 1080)),
+            "window size (0, 1080) must not be empty"
+        );
+        assert_eq!(
+            error(builder().zoom(Some(0.0))),
+            "zoom Some(0.0) must be positive"
+        );
+        assert_eq!(
+            error(builder().slide_policy(SlidePolicy {
+                warn_percent: 110,
+                ..SlidePolicy::default()
+            })),
+            "slide policy warn_percent 110 must not exceed fail_percent 100"
+        );
+        assert_eq!(
+            error(
+                builder().basic_auth(Some((
+                    String::from("user"),
+                    String::from("password")
+                )))
+            ),
+            "basic authentication requires an http(s) html_base_url"
+        );
+        assert!(error(builder().screenshot_dir(Some(PathBuf::from("Cargo.toml"))))
+            .starts_with("output directory Cargo.toml is a file"));
+    }
+
+    #[test]
+    fn failure_message_placeholders_are_replaced() {
+        let slide_policy = SlidePolicy {
+            max_width: 800,
+            max_height: 600,
+            failure_message: Some(String::from(
+                "{path} is {width}x{height}, more than {max_width}x{max_height}",
+            )),
+            ..SlidePolicy::default()
+        };
+        let slide = Slide {
+            filename: Path::new("book/intro.html").into(),
+            source_dir: Path::new("book").into(),
+            fragment: Some(String::from("slide-2")),
+            element_selector: None,
+            content_hash: None,
+        };
+        let element_size =
+            ElementSize { x: 0.0, y: 0.0, width: 812.4, height: 599.6 };
+        assert_eq!(
+            slide_policy
+                .eval_failure_message(
+                    &slide,
+                    &element_size,
+                    Severity::Fail,
+                    |value| RoundingMode::Round.apply(value)
+                )
+                .as_deref(),
+            Some("book/intro.html#slide-2 is 812x600, more than 800x600")
+        );
+        // the dimensions are converted like the exported dimensions, e.g. to rem
+        assert_eq!(
+            slide_policy
+                .eval_failure_message(
+                    &slide,
+                    &element_size,
+                    Severity::Fail,
+                    |value| { RoundingMode::Ceil.apply(value / 100.0) }
+                )
+                .as_deref(),
+            Some("book/intro.html#slide-2 is 9x6, more than 8x6")
+        );
+        assert_eq!(
+            slide_policy.eval_failure_message(
+                &slide,
+                &element_size,
+                Severity::Warn,
+                |value| value
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn policy_overlay_uses_the_policy_of_the_slide() {
+        let white = image::Rgba([255, 255, 255, 255]);
+        let red = image::Rgba([255, 0, 0, 255]);
+        let mut png = io::Cursor::new(vec![]);
+        image::RgbaImage::from_pixel(20, 40, white)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let slide_policy = SlidePolicy {
+            max_width: 5,
+            max_height: 10,
+            class_max_heights: vec![(String::from("tall"), 15)],
+            ..SlidePolicy::default()
+        };
+        let slide_policy = slide_policy
+            .with_max_height(slide_policy.max_height_for_classes("content tall"));
+        // the limits are scaled by the device pixel ratio
+        let overlay =
+            Evaluator::draw_policy_overlay(png.get_ref(), &slide_policy, 2.0)
+                .unwrap();
+        let overlay = image::load_from_memory(&overlay).unwrap().to_rgba8();
+        assert_eq!(*overlay.get_pixel(10, 15), red);
+        assert_eq!(*overlay.get_pixel(5, 30), red);
+        assert_eq!(*overlay.get_pixel(5, 20), white);
+        assert_eq!(*overlay.get_pixel(15, 35), white);
+    }
+
+    #[test]
+    fn screenshots_are_scaled_to_css_pixels() {
+        let mut png = io::Cursor::new(vec![]);
+        image::RgbaImage::from_pixel(40, 30, image::Rgba([255, 255, 255, 255]))
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let scaled = Evaluator::scale_to_css_pixels(png.get_ref(), 2.0).unwrap();
+        let scaled = image::load_from_memory(&scaled).unwrap();
+        assert_eq!((scaled.width(), scaled.height()), (20, 15));
+    }
+
+    #[test]
+    fn blank_screenshots_have_a_single_color() {
+        let png = |image: image::RgbaImage| {
+            let mut png = std::io::Cursor::new(Vec::new());
+            image.write_to(&mut png, image::ImageFormat::Png).unwrap();
+            png.into_inner()
+        };
+        let white = image::Rgba([255, 255, 255, 255]);
+        let single_color = image::RgbaImage::from_pixel(100, 100, white);
+        assert!(is_blank(&png(single_color.clone())).unwrap());
+        // anti-aliasing noise below the channel tolerance is ignored
+        let mut noisy = single_color.clone();
+        noisy.put_pixel(50, 50, image::Rgba([250, 250, 250, 255]));
+        assert!(is_blank(&png(noisy)).unwrap());
+        let mut mixed = single_color;
+        for x in 0..100 {
+            mixed.put_pixel(x, 50, image::Rgba([0, 0, 0, 255]));
+        }
+        assert!(!is_blank(&png(mixed)).unwrap());
+        assert!(is_blank(b"not a png").is_err());
+    }
+
+    #[test]
+    fn join_url_path_keeps_the_base_path() {
+        let join = |base_url: &str, path: &str| {
+            join_url_path(&Url::parse(base_url).unwrap(), Path::new(path))
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            join("https://host/docs/", "intro.html"),
+            "https://host/docs/intro.html"
+        );
+        assert_eq!(
+            join("https://host/docs", "intro.html"),
+            "https://host/docs/intro.html"
+        );
+        assert_eq!(
+            join("https://host/docs/", "/book/html/intro.html"),
+            "https://host/docs/book/html/intro.html"
+        );
+        assert_eq!(join("https://host", "./a/../b.html"), "https://host/b.html");
+        // parent directories stop at the base path
+        assert_eq!(
+            join("https://host/docs/", "../../intro.html"),
+            "https://host/docs/intro.html"
+        );
+        assert_eq!(
+            join("https://host/docs/?version=2#top", "intro.html"),
+            "https://host/docs/intro.html"
+        );
+        assert_eq!(
+            join("file:///", "/book/html/a b#1.html"),
+            "file:///book/html/a%20b%231.html"
+        );
+        assert!(join_url_path(
+            &Url::parse("data:text/html,").unwrap(),
+            Path::new("a")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn max_height_from_the_first_mapped_class() {
+        let slide_policy = SlidePolicy {
+            class_max_heights: vec![
+                (String::from("slide-eval-tall"), 2000),
+                (String::from("slide-eval-taller"), 3000),
+            ],
+            ..SlidePolicy::default()
+        };
+        let max_height = |classes| slide_policy.max_height_for_classes(classes);
+        assert_eq!(
+            max_height("content slide-eval-taller slide-eval-tall"),
+            Some(3000)
+        );
+        assert_eq!(max_height("slide-eval-tall"), Some(2000));
+        assert_eq!(max_height("content slide-eval-tallest"), None);
+        assert_eq!(max_height(""), None);
+    }
+
+    #[test]
+    fn font_faces_of_files_and_urls() {
+        let font = WebFont {
+            family: String::from("Open \"Sans\""),
+            url: String::from("https://host/fonts/open-sans.woff2"),
+        };
+        assert_eq!(
+            font_face_css(&[font]),
+            "@font-face { font-family: \"Open \\\"Sans\\\"\"; \
+             src: url(\"https://host/fonts/open-sans.woff2\"); font-display: block; }\n"
+        );
+        let error =
+            WebFont::from_file("Open Sans", Path::new("open-sans.svg")).unwrap_err();
+        assert_eq!(error.to_string(), "unsupported font file open-sans.svg");
+        assert!(WebFont::from_file("Open Sans", Path::new("/nonexistent.woff2"))
+            .is_err());
+    }
+
+    #[test]
+    fn window_size_from_aspect_ratio() {
+        let ratio: AspectRatio = "16:10".parse().unwrap();
+        assert_eq!(ratio, AspectRatio { width: 16, height: 10 });
+        assert_eq!(ratio.window_size(1920), (1920, 1200));
+        assert_eq!(
+            "4:3".parse::<AspectRatio>().unwrap().window_size(1366),
+            (1366, 1025)
+        );
+        assert!("16x9".parse::<AspectRatio>().is_err());
+        assert!("16:0".parse::<AspectRatio>().is_err());
+    }
+
+    #[test]
+    fn merge_csv_exports_replaces_slides_of_later_inputs() {
+        let first = "source_dir,filename,fragment,element_height
+/ci/a/book,/ci/a/book/intro.html,,900
+/ci/a/book,/ci/a/book/slides.html,slide-1,1400
+";
+        let second = "source_dir,filename,fragment,element_height
+/ci/b/book,/ci/b/book/slides.html,slide-1,1200
+/ci/b/book,/ci/b/book/slides.html,slide-2,800
+";
+        let mut merged = vec![];
+        let count = merge_csv_exports(
+            vec![first.as_bytes(), second.as_bytes()],
+            &mut merged,
+        )
+        .unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(
+            String::from_utf8(merged).unwrap(),
+            "source_dir,filename,fragment,element_height
+/ci/a/book,/ci/a/book/intro.html,,900
+/ci/b/book,/ci/b/book/slides.html,slide-1,1200
+/ci/b/book,/ci/b/book/slides.html,slide-2,800
+"
+        );
+
+        let other_columns = "source_dir,filename,fragment\n";
+        assert!(merge_csv_exports(
+            vec![first.as_bytes(), other_columns.as_bytes()],
+            vec![]
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,240 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal local http server that serves the files of the book to a
+//! browser, e.g. for pages that behave differently when they are opened
+//! with a file:// url. The url path of a file is its path relative to the
+//! served directory.
+
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use url::Url;
+
+/// serves the files below root on an ephemeral port of the loopback
+/// interface. The server shuts down when it is dropped
+pub struct FileServer {
+    /// the base url of the served files
+    base_url: Url,
+    /// the task accepting connections
+    task: JoinHandle<()>,
+}
+
+impl FileServer {
+    /// bind a free port and start serving the files below root
+    pub async fn start(root: &Path) -> anyhow::Result<FileServer> {
+        let root = root.canonicalize()?;
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+        debug!("serving {} at {}", root.display(), base_url);
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let root = root.clone();
+                tokio::spawn(async move {
+                    if let Err(error) = serve_request(stream, &root).await {
+                        warn!("failed to serve request: {}", error);
+                    }
+                });
+            }
+        });
+        Ok(FileServer { base_url, task })
+    }
+
+    /// the base url of the served files
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+}
+
+impl Drop for FileServer {
+    fn drop(&mut self) {
+        debug!("stop serving at {}", self.base_url);
+        self.task.abort();
+    }
+}
+
+/// the content type of a file derived from its extension
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// the file below root that the url path refers to. None if the path
+/// leaves root or the file does not exist
+fn resolve_path(root: &Path, url_path: &str) -> Option<PathBuf> {
+    // decode the path like a file url, the query is ignored
+    let url_path = url_path.split(['?', '#']).next()?;
+    let path =
+        Url::parse(&format!("file://{url_path}")).ok()?.to_file_path().ok()?;
+    let relative_path = path.strip_prefix("/").ok()?;
+    let mut path = root.join(relative_path).canonicalize().ok()?;
+    if !path.starts_with(root) {
+        return None;
+    }
+    if path.is_dir() {
+        path = path.join("index.html");
+    }
+    path.is_file().then_some(path)
+}
+
+/// answer a single GET request with the requested file and close the
+/// connection
+async fn serve_request(stream: TcpStream, root: &Path) -> anyhow::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    // skip the headers, requests for files have no body
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let (method, url_path) = (parts.next(), parts.next());
+    let file = match (method, url_path) {
+        (Some("GET"), Some(url_path)) => resolve_path(root, url_path),
+        _ => None,
+    };
+    let (status, content_type, body) = match file {
+        Some(file) => ("200 OK", content_type(&file), tokio::fs::read(&file).await?),
+        None => ("404 Not Found", "text/plain", b"not found".to_vec()),
+    };
+    let mut stream = reader.into_inner();
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            )
+            .as_bytes(),
+        )
+        .await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    /// request the url path from the server and return the response
+    async fn get(server: &FileServer, url_path: &str) -> String {
+        let address = format!(
+            "{}:{}",
+            server.base_url().host_str().unwrap(),
+            server.base_url().port().unwrap()
+        );
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(
+                format!("GET {url_path} HTTP/1.1\r\nhost: test\r\n\r\n").as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn serves_files_below_root_only() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/book");
+        let server = FileServer::start(&root).await.unwrap();
+        let response = get(&server, "/fits.html").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("content-type: text/html"));
+        assert!(response.contains("<h1>fits</h1>"));
+
+        // files outside of the served directory are not found
+        let response = get(&server, "/../../evaluator.rs").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn url_paths_are_percent_decoded() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = root_dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("dir")).unwrap();
+        std::fs::write(root.join("dir/index.html"), "").unwrap();
+        std::fs::write(root.join("a b.html"), "").unwrap();
+        std::fs::write(root.join("ü.html"), "").unwrap();
+
+        assert_eq!(resolve_path(&root, "/a%20b.html"), Some(root.join("a b.html")));
+        assert_eq!(resolve_path(&root, "/%C3%BC.html"), Some(root.join("ü.html")));
+        assert_eq!(
+            resolve_path(&root, "/a%20b.html?q=%2F#top"),
+            Some(root.join("a b.html"))
+        );
+        assert_eq!(resolve_path(&root, "/dir/"), Some(root.join("dir/index.html")));
+        assert_eq!(resolve_path(&root, "/a%2520b.html"), None);
+    }
+
+    #[test]
+    fn url_paths_cannot_leave_root() {
+        let parent_dir = tempfile::tempdir().unwrap();
+        let parent = parent_dir.path().canonicalize().unwrap();
+        let root = parent.join("book");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(parent.join("secret.html"), "").unwrap();
+        std::fs::write(root.join("index.html"), "").unwrap();
+
+        for url_path in [
+            "/../secret.html",
+            "/%2e%2e/secret.html",
+            "/%2E%2E/secret.html",
+            "/..%2fsecret.html",
+            "/..%2Fsecret.html",
+            "/dir/..%2f..%2fsecret.html",
+            "/..%5csecret.html",
+        ] {
+            assert_eq!(resolve_path(&root, url_path), None, "{url_path}");
+        }
+        // dot segments are resolved like in a browser, they never go above
+        // the root of the url
+        assert_eq!(
+            resolve_path(&root, "/dir/%2e%2e/index.html"),
+            Some(root.join("index.html"))
+        );
+
+        // symbolic links may not point outside of root
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(
+                parent.join("secret.html"),
+                root.join("link.html"),
+            )
+            .unwrap();
+            assert_eq!(resolve_path(&root, "/link.html"), None);
+        }
+    }
+}
@@ -12,5 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+/// a script executed in the browser. The name in its first line marks the
+/// script in the webdriver logs and lets a stub webdriver tell the scripts
+/// apart
+macro_rules! script {
+    ($name:literal, $body:literal) => {
+        concat!("// slide-evaluator: ", $name, "\n", $body)
+    };
+}
+
+pub mod cdp;
+pub mod estimate;
 pub mod evaluator;
+pub mod file_server;
+pub mod metrics;
+pub mod pdf;
 pub mod slides;
@@ -0,0 +1,102 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Custom per-slide metrics. A metric measures a named value of the
+//! evaluated element of the opened slide. The metrics in the registry of the
+//! evaluator are measured for every slide and exported with the results, so
+//! new metrics do not require changes to the evaluator.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use anyhow::anyhow;
+use fantoccini::elements::Element;
+use fantoccini::Client;
+
+/// the future returned by [SlideMetric::measure]
+pub type MetricFuture<'a> =
+    Pin<Box<dyn Future<Output = anyhow::Result<f64>> + Send + 'a>>;
+
+/// a value measured for each slide
+pub trait SlideMetric: Send + Sync {
+    /// the name of the metric in the exports, e.g. node_count
+    fn name(&self) -> &str;
+
+    /// measure the metric of the element in the slide that is opened in the
+    /// webclient
+    fn measure<'a>(
+        &'a self,
+        webclient: &'a Client,
+        element: &'a Element,
+    ) -> MetricFuture<'a>;
+}
+
+/// the metrics measured for each slide in the order of registration
+#[derive(Default)]
+pub struct MetricRegistry {
+    metrics: Vec<Box<dyn SlideMetric>>,
+}
+
+impl MetricRegistry {
+    /// add the metric to the registry
+    pub fn register(&mut self, metric: Box<dyn SlideMetric>) {
+        self.metrics.push(metric);
+    }
+
+    /// measure all metrics of the element. Returns the name and value of
+    /// each metric
+    pub async fn measure_all(
+        &self,
+        webclient: &Client,
+        element: &Element,
+    ) -> anyhow::Result<Vec<(String, f64)>> {
+        let mut values = vec![];
+        for metric in &self.metrics {
+            let value = metric.measure(webclient, element).await?;
+            values.push((metric.name().to_string(), value));
+        }
+        Ok(values)
+    }
+}
+
+/// the number of elements inside the element, a rough measure of the
+/// complexity of a slide
+pub struct NodeCount;
+
+impl SlideMetric for NodeCount {
+    fn name(&self) -> &str {
+        "node_count"
+    }
+
+    fn measure<'a>(
+        &'a self,
+        webclient: &'a Client,
+        element: &'a Element,
+    ) -> MetricFuture<'a> {
+        Box::pin(async move {
+            let node_count = webclient
+                .execute(
+                    script!(
+                        "node-count",
+                        "return arguments[0].querySelectorAll('*').length"
+                    ),
+                    vec![serde_json::to_value(element)?],
+                )
+                .await?;
+            node_count
+                .as_f64()
+                .ok_or_else(|| anyhow!("invalid node count {node_count}"))
+        })
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal PDF writer for contact sheets of screenshots. Each page shows
+//! one image with a single line caption below it. The images are embedded as
+//! deflate compressed RGB samples and the caption uses the standard
+//! Helvetica font, so no font or PDF library is needed. The pages are
+//! written as they are added, so large books do not have to fit into memory.
+
+use std::io::{self, Write as _};
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::RgbImage;
+
+/// the height of the caption band below the image in points
+const CAPTION_HEIGHT: u32 = 30;
+/// the font size of the caption in points
+const CAPTION_FONT_SIZE: u32 = 12;
+/// the largest page dimension PDF viewers have to support in points
+const MAX_PAGE_SIZE: f64 = 14400.0;
+
+/// a page of the contact sheet
+pub struct ContactSheetPage {
+    /// the image shown on the page. One pixel is one point
+    pub image: RgbImage,
+    /// the caption below the image
+    pub caption: String,
+    /// highlight the caption in red, e.g. for overflowing slides
+    pub highlight: bool,
+}
+
+/// escape the caption for a PDF string literal. Characters outside of
+/// printable ASCII are replaced, the standard font only covers Latin-1 and
+/// the encoding of the string is not declared
+fn escape_caption(caption: &str) -> String {
+    caption
+        .chars()
+        .map(|c| match c {
+            '(' | ')' | '\\' => format!("\\{c}"),
+            ' '..='~' => c.to_string(),
+            _ => String::from("?"),
+        })
+        .collect()
+}
+
+/// counts the bytes written, for the byte offsets of the objects
+struct CountingWriter<W> {
+    inner: W,
+    count: usize,
+}
+
+impl<W: io::Write> io::Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// the object number of the catalog
+const CATALOG_ID: usize = 1;
+/// the object number of the page tree
+const PAGES_ID: usize = 2;
+/// the object number of the caption font
+const FONT_ID: usize = 3;
+
+/// writes a contact sheet page by page, so only the image of the current
+/// page is held in memory. The objects are written as soon as they are
+/// created and their byte offsets are recorded for the xref table
+pub struct ContactSheetWriter<W: io::Write> {
+    writer: CountingWriter<W>,
+    /// the byte offset of each object by its number - 1
+    offsets: Vec<usize>,
+    /// the object numbers of the pages
+    page_ids: Vec<usize>,
+}
+
+impl<W: io::Write> ContactSheetWriter<W> {
+    /// write the header of the document
+    pub fn new(writer: W) -> io::Result<ContactSheetWriter<W>> {
+        let mut pdf = ContactSheetWriter {
+            writer: CountingWriter { inner: writer, count: 0 },
+            // the catalog and the page tree are written by finish
+            offsets: vec![0; FONT_ID],
+            page_ids: vec![],
+        };
+        pdf.writer.write_all(b"%PDF-1.4\n")?;
+        pdf.object(
+            FONT_ID,
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>",
+        )?;
+        Ok(pdf)
+    }
+
+    /// reserve the next object number
+    fn next_id(&mut self) -> usize {
+        self.offsets.push(0);
+        self.offsets.len()
+    }
+
+    /// record the offset of the object and write its header
+    fn begin_object(&mut self, id: usize) -> io::Result<()> {
+        self.offsets[id - 1] = self.writer.count;
+        writeln!(self.writer, "{id} 0 obj")
+    }
+
+    /// write an object with the given number
+    fn object(&mut self, id: usize, content: &[u8]) -> io::Result<()> {
+        self.begin_object(id)?;
+        self.writer.write_all(content)?;
+        self.writer.write_all(b"\nendobj\n")
+    }
+
+    /// write a page with its image and caption
+    pub fn add_page(&mut self, page: &ContactSheetPage) -> io::Result<()> {
+        // each page uses four objects: the page, its content, its image and
+        // the length of the image stream, which is only known once the
+        // image is compressed
+        let page_id = self.next_id();
+        let content_id = self.next_id();
+        let image_id = self.next_id();
+        let length_id = self.next_id();
+
+        let (width, height) = page.image.dimensions();
+        // scale down images that exceed the maximum page size
+        let scale = f64::min(
+            1.0,
+            MAX_PAGE_SIZE / f64::max(width as f64, (height + CAPTION_HEIGHT) as f64),
+        );
+        let image_width = width as f64 * scale;
+        let image_height = height as f64 * scale;
+        let page_width = image_width.max(1.0);
+        let page_height = image_height + CAPTION_HEIGHT as f64;
+        self.object(
+            page_id,
+            format!(
+                "<< /Type /Page /Parent {PAGES_ID} 0 R \
+                 /MediaBox [0 0 {page_width} {page_height}] \
+                 /Resources << /Font << /F1 {FONT_ID} 0 R >> \
+                 /XObject << /Im1 {image_id} 0 R >> >> /Contents {content_id} 0 R >>"
+            )
+            .as_bytes(),
+        )?;
+
+        let color = if page.highlight { "1 0 0 rg" } else { "0 g" };
+        let content = format!(
+            "q {image_width} 0 0 {image_height} 0 {CAPTION_HEIGHT} cm /Im1 Do Q\n\
+             BT {color} /F1 {CAPTION_FONT_SIZE} Tf 8 10 Td ({}) Tj ET",
+            escape_caption(&page.caption)
+        );
+        self.object(
+            content_id,
+            format!("<< /Length {} >>\nstream\n{content}\nendstream", content.len())
+                .as_bytes(),
+        )?;
+
+        self.begin_object(image_id)?;
+        write!(
+            self.writer,
+            "<< /Type /XObject /Subtype /Image /Width {width} /Height {height} \
+             /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode \
+             /Length {length_id} 0 R >>\nstream\n"
+        )?;
+        let stream_start = self.writer.count;
+        let mut encoder = ZlibEncoder::new(&mut self.writer, Compression::default());
+        encoder.write_all(page.image.as_raw())?;
+        encoder.finish()?;
+        let stream_length = self.writer.count - stream_start;
+        self.writer.write_all(b"\nendstream\nendobj\n")?;
+        self.object(length_id, stream_length.to_string().as_bytes())?;
+
+        self.page_ids.push(page_id);
+        Ok(())
+    }
+
+    /// write the page tree, the catalog and the xref table and return the
+    /// writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let kids: Vec<String> =
+            self.page_ids.iter().map(|id| format!("{id} 0 R")).collect();
+        self.object(
+            PAGES_ID,
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                self.page_ids.len()
+            )
+            .as_bytes(),
+        )?;
+        self.object(
+            CATALOG_ID,
+            format!("<< /Type /Catalog /Pages {PAGES_ID} 0 R >>").as_bytes(),
+        )?;
+
+        let xref_offset = self.writer.count;
+        write!(
+            self.writer,
+            "xref\n0 {}\n0000000000 65535 f \n",
+            self.offsets.len() + 1
+        )?;
+        for offset in &self.offsets {
+            writeln!(self.writer, "{offset:010} 00000 n ")?;
+        }
+        write!(
+            self.writer,
+            "trailer\n<< /Size {} /Root {CATALOG_ID} 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+            self.offsets.len() + 1
+        )?;
+        self.writer.flush()?;
+        Ok(self.writer.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contact_sheet_with_two_pages() {
+        let page = |caption: &str, highlight| ContactSheetPage {
+            image: RgbImage::new(40, 20),
+            caption: caption.to_string(),
+            highlight,
+        };
+        let mut pdf = ContactSheetWriter::new(vec![]).unwrap();
+        pdf.add_page(&page("intro.html", false)).unwrap();
+        pdf.add_page(&page("basics (1).html", true)).unwrap();
+        let pdf = pdf.finish().unwrap();
+        let contains =
+            |needle: &[u8]| pdf.windows(needle.len()).any(|w| w == needle);
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(contains(b"/Kids [4 0 R 8 0 R] /Count 2"));
+        // the length of the image stream is written after the stream
+        assert!(contains(b"/Length 7 0 R >>\nstream\n"));
+        assert!(contains(b"(basics \\(1\\).html) Tj"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        // the xref table points at the objects
+        let trailer = String::from_utf8(pdf[pdf.len() - 32..].to_vec()).unwrap();
+        let xref_offset: usize =
+            trailer.lines().rev().nth(1).unwrap().parse().unwrap();
+        let xref = String::from_utf8(pdf[xref_offset..].to_vec()).unwrap();
+        assert!(xref.starts_with("xref\n0 12\n"));
+        for object in 1..12 {
+            let offset: usize =
+                xref.lines().nth(2 + object).unwrap()[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{object} 0 obj").as_bytes()));
+        }
+    }
+}
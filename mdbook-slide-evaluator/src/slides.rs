@@ -12,21 +12,400 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
+use std::io::{self, Read as _};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::SystemTime;
+use std::{fmt, fs};
 
-use log::debug;
+use anyhow::{anyhow, Context as _};
+use flate2::read::GzDecoder;
+use log::{debug, warn};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 
-/// a slide is a page in the book
+use crate::evaluator::OutputLayout;
+
+/// a slide is a page in the book or a section of a page starting at an
+/// anchor
 #[derive(Debug, Clone)]
 pub struct Slide {
     pub filename: Arc<Path>,
+    /// the source directory of the book this slide was found in
+    pub source_dir: Arc<Path>,
+    /// the id of the anchor this slide starts at if the page contains
+    /// multiple slides
+    pub fragment: Option<String>,
+    /// the XPath to the evaluated element if it differs from the default
+    pub element_selector: Option<Arc<str>>,
+    /// the hash of the content of the page, see [page_content_hash] and
+    /// [Slide::id]. None if the page cannot be read
+    pub content_hash: Option<u64>,
+}
+
+/// the 64 bit FNV-1a hash of the content. Unlike the hasher of the standard
+/// library the hash is stable across Rust versions and platforms
+pub fn content_hash(content: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    content.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// the content hash of the main element of the page. The main element of an
+/// mdbook page holds the chapter without the sidebar and the links to the
+/// previous and next chapter, which change when other chapters are added or
+/// renamed. Pages without a main element are hashed completely
+pub fn page_content_hash(html: &str) -> u64 {
+    let main = html
+        .match_indices("<main")
+        .find(|(index, tag)| {
+            html[index + tag.len()..]
+                .starts_with(|c: char| c.is_whitespace() || c == '>')
+        })
+        .and_then(|(start, _)| {
+            let end = html.rfind("</main>")?;
+            (end > start).then(|| &html[start..end + "</main>".len()])
+        });
+    content_hash(main.unwrap_or(html).as_bytes())
+}
+
+impl Slide {
+    /// an id of the slide derived from the content of its page and its
+    /// fragment. It stays the same if the page is renamed or moved, e.g. to
+    /// follow a slide across runs, and changes when the page is edited. None
+    /// if the page could not be read to compute the hash
+    pub fn id(&self) -> Option<String> {
+        let content_hash = self.content_hash?;
+        Some(match &self.fragment {
+            Some(fragment) => format!("{content_hash:016x}#{fragment}"),
+            None => format!("{content_hash:016x}"),
+        })
+    }
+
+    /// true if the slide is a gzip compressed html file
+    pub fn is_gzipped(&self) -> bool {
+        self.filename.extension() == Some("gz".as_ref())
+    }
+
+    /// the filename of the slide without the .gz extension of a gzip
+    /// compressed slide
+    pub fn html_filename(&self) -> PathBuf {
+        if self.is_gzipped() {
+            self.filename.with_extension("")
+        } else {
+            self.filename.to_path_buf()
+        }
+    }
+
+    /// read the html of the slide and decompress it if necessary
+    pub fn read_html(&self) -> anyhow::Result<Vec<u8>> {
+        let content = fs::read(&self.filename)
+            .with_context(|| format!("cannot read {}", self.filename.display()))?;
+        if !self.is_gzipped() {
+            return Ok(content);
+        }
+        let mut html = vec![];
+        GzDecoder::new(content.as_slice()).read_to_end(&mut html).with_context(
+            || format!("cannot decompress {}", self.filename.display()),
+        )?;
+        Ok(html)
+    }
+
+    /// read the html of the slide as text. Files that are not valid utf-8 are
+    /// decoded as Latin-1 (ISO-8859-1) and reported, so a single file in a
+    /// legacy encoding does not abort the evaluation of the book
+    pub fn read_html_text(&self) -> anyhow::Result<String> {
+        match String::from_utf8(self.read_html()?) {
+            Ok(html) => Ok(html),
+            Err(error) => {
+                warn!("{} is not valid utf-8, decode it as Latin-1", self);
+                Ok(decode_latin1(error.as_bytes()))
+            }
+        }
+    }
+
+    /// the path of an output file (e.g. a screenshot) for the slide relative
+    /// to the output directory. It mirrors the location of the slide
+    /// relative to base_dir with the given extension. The fragment of a
+    /// slide is added to the file name
+    pub fn relative_output_path(
+        &self,
+        base_dir: &Path,
+        extension: &str,
+    ) -> anyhow::Result<PathBuf> {
+        let html_filename = self.html_filename();
+        let relative_filename = html_filename.strip_prefix(base_dir)?;
+        let extension = match &self.fragment {
+            Some(fragment) => format!("{fragment}.{extension}"),
+            None => extension.to_string(),
+        };
+        Ok(relative_filename.with_extension(&extension))
+    }
+}
+
+impl fmt::Display for Slide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.filename.display())?;
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{fragment}")?;
+        }
+        Ok(())
+    }
+}
+
+/// decode Latin-1 (ISO-8859-1) text. Each byte is the code point of its
+/// character, so every byte sequence is valid
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+/// return the ids of all elements in the html that start with the given
+/// prefix in document order. Ids in comments and attributes that only end
+/// with id (e.g. data-id) are ignored
+fn find_anchor_ids(html: &str, id_prefix: &str) -> Vec<String> {
+    let mut ids = vec![];
+    let mut rest = html;
+    while let Some(index) = rest.find('<') {
+        rest = &rest[index..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.split_once("-->").map_or("", |(_, rest)| rest);
+            continue;
+        }
+        let end = rest.find('>').unwrap_or(rest.len());
+        if let Some(id) = start_tag_id(&rest[..end]) {
+            if id.starts_with(id_prefix) {
+                ids.push(id);
+            }
+        }
+        rest = &rest[end..];
+    }
+    ids
+}
+
+/// the value of the id attribute of a start tag, e.g. `<section id="a"`
+fn start_tag_id(start_tag: &str) -> Option<String> {
+    start_tag
+        .match_indices(r#"id=""#)
+        .find(|(index, _)| start_tag[..*index].ends_with(char::is_whitespace))
+        .and_then(|(index, _)| {
+            let id = &start_tag[index + r#"id=""#.len()..];
+            id.find('"').map(|end| id[..end].to_string())
+        })
+}
+
+/// return the id of each element with the given tag name in the html in
+/// document order. Elements without an id are returned as none
+fn find_element_ids(html: &str, tag: &str) -> Vec<Option<String>> {
+    let pattern = format!("<{tag}");
+    html.match_indices(&pattern)
+        .filter_map(|(index, _)| {
+            let element = &html[index + pattern.len()..];
+            // skip elements whose name only starts with the tag name
+            if !element.starts_with(|c: char| c.is_whitespace() || c == '>') {
+                return None;
+            }
+            let start_tag = &element[..element.find('>').unwrap_or(element.len())];
+            Some(start_tag_id(start_tag))
+        })
+        .collect()
+}
+
+/// true if the head of the html contains a refresh meta tag, e.g. the
+/// redirect pages mdbook creates for moved chapters
+fn file_is_redirect(html: &str) -> bool {
+    let html = html.to_lowercase();
+    let head = match html.find("</head>") {
+        Some(end) => &html[..end],
+        None => &html,
+    };
+    head.match_indices("<meta").any(|(index, _)| {
+        let tag = &head[index..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        tag.replace(['"', '\''], "").contains("http-equiv=refresh")
+    })
+}
+
+/// return the html pages mdbook renders for the chapters linked in a
+/// SUMMARY.md, relative to the output directory of the book. Draft chapters
+/// without a link and external links are left out
+pub fn summary_html_pages(summary: &str) -> Vec<PathBuf> {
+    summary
+        .match_indices("](")
+        .filter_map(|(index, _)| {
+            let link = &summary[index + "](".len()..];
+            link.find(')').map(|end| &link[..end])
+        })
+        .filter(|link| !link.is_empty() && !link.contains("://"))
+        .map(|link| match Path::new(link) {
+            // mdbook renders a README.md as the index of its directory
+            path if path.file_name() == Some("README.md".as_ref()) => {
+                path.with_file_name("index.html")
+            }
+            path => path.with_extension("html"),
+        })
+        .collect()
+}
+
+/// the file in a source directory that lists the slides to ignore
+const IGNORE_FILE: &str = ".slideevalignore";
+
+/// a pattern of an ignore file. Negated patterns include slides again that
+/// were ignored by a previous pattern
+struct IgnorePattern {
+    /// true if the pattern starts with !
+    negated: bool,
+    /// the glob pattern matched against the path relative to the source
+    /// directory
+    pattern: glob::Pattern,
+}
+
+/// parse the patterns of an ignore file with one glob pattern per line.
+/// Empty lines and lines starting with # are skipped
+fn parse_ignore_file(content: &str) -> anyhow::Result<Vec<IgnorePattern>> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, line),
+            };
+            Ok(IgnorePattern { negated, pattern: glob::Pattern::new(pattern)? })
+        })
+        .collect()
+}
+
+/// true if the last pattern that matches the relative path is not negated
+fn is_ignored(patterns: &[IgnorePattern], relative_path: &Path) -> bool {
+    patterns
+        .iter()
+        .rev()
+        .find(|ignore| ignore.pattern.matches_path(relative_path))
+        .is_some_and(|ignore| !ignore.negated)
+}
+
+/// return a slide for each file with the given extension in source_dir that
+/// is not ignored by the ignore file of source_dir
+fn find_slides(source_dir: &Path, extension: &str) -> anyhow::Result<Vec<Slide>> {
+    let source_dir: Arc<Path> = source_dir.into();
+    let ignore_file = source_dir.join(IGNORE_FILE);
+    let ignore_patterns = match fs::read_to_string(&ignore_file) {
+        Ok(content) => parse_ignore_file(&content)?,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => vec![],
+        Err(error) => Err(error)
+            .with_context(|| format!("cannot read {}", ignore_file.display()))?,
+    };
+    let files = glob::glob(&format!(
+        "{}/**/*.{extension}",
+        source_dir.to_str().expect("invalid path")
+    ))?;
+    let mut slides = vec![];
+    for file in files {
+        let file = file?;
+        if is_ignored(&ignore_patterns, file.strip_prefix(&source_dir)?) {
+            debug!("ignore {}", file.display());
+            continue;
+        }
+        let mut slide = Slide {
+            filename: file.into(),
+            source_dir: source_dir.clone(),
+            fragment: None,
+            element_selector: None,
+            content_hash: None,
+        };
+        // a page that cannot be read fails when it is evaluated, it does not
+        // abort the discovery of the other pages
+        match slide.read_html_text() {
+            Ok(html) => slide.content_hash = Some(page_content_hash(&html)),
+            Err(error) => warn!("cannot hash {}: {:#}", slide, error),
+        }
+        debug!("add {:?}", slide);
+        slides.push(slide);
+    }
+    Ok(slides)
+}
+
+/// where and how the slides of a book are discovered. This is the same
+/// discovery the evaluator binary runs before evaluating, without any
+/// browser interaction, e.g. to list the slides of a book
+#[derive(Debug, Clone)]
+pub struct SlideDiscovery {
+    /// the root directories of the book
+    pub source_dirs: Vec<PathBuf>,
+    /// a single html file that contains all slides. Used instead of
+    /// source_dirs if set
+    pub single_file: Option<PathBuf>,
+    /// the tag name of the elements that separate the slides of single_file
+    pub separator: String,
+    /// also discover gzip compressed slides (*.html.gz)
+    pub gzip: bool,
+    /// split pages into one slide per anchor with an id with this prefix
+    pub anchor_prefix: Option<String>,
+    /// the XPath of the evaluated element for slides matching a glob pattern
+    pub element_selectors: Vec<(glob::Pattern, String)>,
+    /// shuffle the slides with this seed
+    pub shuffle: Option<u64>,
+    /// only discover slides whose file was modified after this time
+    pub modified_since: Option<SystemTime>,
+}
+
+impl SlideDiscovery {
+    /// discover the html files in the source_dirs with the default options
+    pub fn new(source_dirs: Vec<PathBuf>) -> SlideDiscovery {
+        SlideDiscovery {
+            source_dirs,
+            single_file: None,
+            separator: String::from("section"),
+            gzip: false,
+            anchor_prefix: None,
+            element_selectors: vec![],
+            shuffle: None,
+            modified_since: None,
+        }
+    }
+
+    /// discover the slides of the book. Redirect pages are left out
+    pub fn book(&self) -> anyhow::Result<Book> {
+        let mut book = match &self.single_file {
+            Some(single_file) => {
+                Book::from_single_html_file(single_file.clone(), &self.separator)?
+            }
+            None => Book::from_html_slides_in(self.source_dirs.clone())?,
+        };
+        if self.gzip {
+            book = book.with_gzipped_slides()?;
+        }
+        if let Some(modified_since) = self.modified_since {
+            book = book.modified_since(modified_since)?;
+        }
+        book = book.without_redirects()?;
+        if let Some(anchor_prefix) = &self.anchor_prefix {
+            book = book.split_by_anchors(anchor_prefix)?;
+        }
+        if !self.element_selectors.is_empty() {
+            book = book.assign_element_selectors(&self.element_selectors)?;
+        }
+        if let Some(seed) = self.shuffle {
+            book = book.shuffle(seed);
+        }
+        Ok(book)
+    }
+
+    /// discover the slides of the book, see [SlideDiscovery::book]
+    pub fn slides(&self) -> anyhow::Result<Vec<Slide>> {
+        Ok(self.book()?.into_slides())
+    }
 }
 
 /// a book is a collection of slides
 pub struct Book {
-    /// the path to the root directory of this book
-    _source_dir: PathBuf,
+    /// the paths to the root directories of this book
+    source_dirs: Vec<PathBuf>,
     /// the collection of slides
     slides: Vec<Slide>,
 }
@@ -34,21 +413,568 @@ pub struct Book {
 impl Book {
     /// create a book from all html files in the source_dir
     pub fn from_html_slides(source_dir: PathBuf) -> anyhow::Result<Book> {
+        Book::from_html_slides_in(vec![source_dir])
+    }
+
+    /// create a single book from all html files in all source_dirs. Each
+    /// slide remembers the source directory it was found in. Files listed in
+    /// the .slideevalignore file of a source directory are left out
+    pub fn from_html_slides_in(source_dirs: Vec<PathBuf>) -> anyhow::Result<Book> {
         let mut slides = vec![];
+        for source_dir in &source_dirs {
+            slides.extend(find_slides(source_dir, "html")?);
+        }
+        // a stable order makes partial evaluations reproducible
+        slides.sort_by(|a, b| a.filename.cmp(&b.filename));
+        Ok(Book { source_dirs, slides })
+    }
+
+    /// create a book from a single html file that contains all slides, e.g.
+    /// a concatenated or print version of a book. Each element with the
+    /// separator tag name (e.g. "section") becomes a slide that starts at the
+    /// id of the element. Elements without an id cannot be addressed and are
+    /// left out
+    pub fn from_single_html_file(
+        file: PathBuf,
+        separator: &str,
+    ) -> anyhow::Result<Book> {
+        let source_dir = file
+            .parent()
+            .ok_or_else(|| anyhow!("{} has no parent directory", file.display()))?
+            .to_path_buf();
+        let mut page = Slide {
+            filename: file.into(),
+            source_dir: source_dir.clone().into(),
+            fragment: None,
+            element_selector: None,
+            content_hash: None,
+        };
+        let html = page.read_html_text()?;
+        page.content_hash = Some(page_content_hash(&html));
+        let mut slides = vec![];
+        for id in find_element_ids(&html, separator) {
+            let Some(id) = id else {
+                warn!("{} without id in {} - ignore", separator, page);
+                continue;
+            };
+            let slide = Slide { fragment: Some(id), ..page.clone() };
+            debug!("add {:?}", slide);
+            slides.push(slide);
+        }
+        Ok(Book { source_dirs: vec![source_dir], slides })
+    }
+
+    /// add all gzip compressed html files (*.html.gz) in the source
+    /// directories of this book as slides. A compressed file next to an
+    /// uncompressed one of the same name is rejected, both would be stored
+    /// to the same output files
+    pub fn with_gzipped_slides(mut self) -> anyhow::Result<Book> {
+        let html_filenames: HashSet<PathBuf> =
+            self.slides.iter().map(Slide::html_filename).collect();
+        for source_dir in &self.source_dirs {
+            for slide in find_slides(source_dir, "html.gz")? {
+                if html_filenames.contains(&slide.html_filename()) {
+                    Err(anyhow!(
+                        "{} and {} are the same slide, remove one of them",
+                        slide.filename.display(),
+                        slide.html_filename().display()
+                    ))?;
+                }
+                self.slides.push(slide);
+            }
+        }
+        self.slides.sort_by(|a, b| a.filename.cmp(&b.filename));
+        Ok(self)
+    }
+
+    /// the deepest directory that contains all source directories of this
+    /// book. Paths relative to it are unique across all source directories
+    pub fn common_source_dir(&self) -> PathBuf {
+        let mut source_dirs = self.source_dirs.iter();
+        let Some(first) = source_dirs.next() else {
+            return PathBuf::new();
+        };
+        source_dirs.fold(first.clone(), |common, source_dir| {
+            common
+                .components()
+                .zip(source_dir.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect()
+        })
+    }
+
+    /// split every page that contains anchors with an id starting with
+    /// id_prefix into one slide per anchor. Pages without such anchors stay
+    /// a single slide
+    pub fn split_by_anchors(self, id_prefix: &str) -> anyhow::Result<Book> {
+        let mut slides = vec![];
+        for slide in self.slides {
+            let html = slide.read_html_text()?;
+            let anchor_ids = find_anchor_ids(&html, id_prefix);
+            if anchor_ids.is_empty() {
+                slides.push(slide);
+                continue;
+            }
+            for anchor_id in anchor_ids {
+                let slide = Slide { fragment: Some(anchor_id), ..slide.clone() };
+                debug!("add {:?}", slide);
+                slides.push(slide);
+            }
+        }
+        Ok(Book { slides, ..self })
+    }
+
+    /// keep only the slides whose file was modified after the given time,
+    /// e.g. for a quick check of the slides changed since the last run
+    pub fn modified_since(mut self, time: SystemTime) -> anyhow::Result<Book> {
+        let mut slides = vec![];
+        for slide in self.slides {
+            let modified = fs::metadata(&slide.filename)
+                .and_then(|metadata| metadata.modified())
+                .with_context(|| {
+                    format!("cannot read the mtime of {}", slide.filename.display())
+                })?;
+            if modified <= time {
+                debug!("ignore unmodified {}", slide);
+                continue;
+            }
+            slides.push(slide);
+        }
+        self.slides = slides;
+        Ok(self)
+    }
+
+    /// remove the redirect pages from the book. They forward to another page
+    /// and have no content of their own. Pages that cannot be read are kept
+    /// and fail when they are evaluated
+    pub fn without_redirects(mut self) -> anyhow::Result<Book> {
+        let mut slides = vec![];
+        for slide in self.slides {
+            let is_redirect = match slide.read_html_text() {
+                Ok(html) => file_is_redirect(&html),
+                Err(error) => {
+                    warn!("cannot check if {} is a redirect: {:#}", slide, error);
+                    false
+                }
+            };
+            if is_redirect {
+                debug!("ignore redirect {}", slide);
+                continue;
+            }
+            slides.push(slide);
+        }
+        self.slides = slides;
+        Ok(self)
+    }
+
+    /// assign the XPath of the first matching glob pattern to each slide. The
+    /// patterns are matched against the path of the slide relative to its
+    /// source directory. Slides without a match keep the default selector
+    pub fn assign_element_selectors(
+        mut self,
+        selectors: &[(glob::Pattern, String)],
+    ) -> anyhow::Result<Book> {
+        for slide in &mut self.slides {
+            let relative_filename =
+                slide.filename.strip_prefix(&slide.source_dir)?;
+            slide.element_selector = selectors
+                .iter()
+                .find(|(pattern, _)| pattern.matches_path(relative_filename))
+                .map(|(_, selector)| selector.as_str().into());
+            debug!("selector for {}: {:?}", slide, slide.element_selector);
+        }
+        Ok(self)
+    }
+
+    /// remove all files with the given extension in output_dir that do not
+    /// belong to a slide of this book, e.g. screenshots of deleted slides.
+    /// The files are expected in the given output layout, the flat layout
+    /// only has files at the top of output_dir. The dated layout keeps a
+    /// history of the runs and is not cleaned. Returns the stale files. They
+    /// are only reported if dry_run is set
+    pub fn remove_stale_output_files(
+        &self,
+        output_dir: &Path,
+        output_layout: OutputLayout,
+        extension: &str,
+        dry_run: bool,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let pattern = match output_layout {
+            OutputLayout::Mirror => format!("**/*.{extension}"),
+            OutputLayout::Flat => format!("*.{extension}"),
+            OutputLayout::Dated => Err(anyhow!(
+                "the dated output layout keeps the files of each run, remove the \
+                 directories of old runs instead"
+            ))?,
+        };
+        let base_dir = self.common_source_dir();
+        let current_files = self
+            .slides
+            .iter()
+            .map(|slide| {
+                let relative_path =
+                    slide.relative_output_path(&base_dir, extension)?;
+                // the date is only used by the dated layout
+                Ok(output_dir.join(output_layout.output_path(&relative_path, "")))
+            })
+            .collect::<anyhow::Result<HashSet<_>>>()?;
+        let mut stale_files = vec![];
         let files = glob::glob(&format!(
-            "{}/**/*.html",
-            source_dir.to_str().expect("invalid path")
+            "{}/{pattern}",
+            output_dir.to_str().expect("invalid path")
         ))?;
         for file in files {
-            let slide = Slide { filename: file?.into() };
-            debug!("add {:?}", slide);
-            slides.push(slide);
+            let file = file?;
+            // directories and links with the extension are left alone
+            let is_file = fs::symlink_metadata(&file)
+                .with_context(|| format!("cannot read {}", file.display()))?
+                .is_file();
+            if !is_file || current_files.contains(&file) {
+                continue;
+            }
+            if !dry_run {
+                debug!("remove {}", file.display());
+                fs::remove_file(&file)
+                    .with_context(|| format!("cannot remove {}", file.display()))?;
+            }
+            stale_files.push(file);
         }
-        Ok(Book { _source_dir: source_dir, slides })
+        Ok(stale_files)
+    }
+
+    /// shuffle the slides with the given seed. The same seed always results
+    /// in the same order for the same slides
+    pub fn shuffle(mut self, seed: u64) -> Book {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        self.slides.shuffle(&mut rng);
+        self
     }
 
     /// return a reference to the slides of this book
     pub fn slides(&self) -> &[Slide] {
         &self.slides
     }
+
+    /// consume the book and return its slides
+    pub fn into_slides(self) -> Vec<Slide> {
+        self.slides
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_anchor_ids_with_prefix() {
+        let html = r#"<h1 id="intro">Intro</h1>
+<section id="slide-1"><p>a</p></section>
+<div data-id="slide-x"></div>
+<!-- <section id="slide-old"></section> -->
+<pre><code>&lt;a id="slide-code"&gt;</code></pre>
+<section class="slide" id="slide-2"><p>b</p></section>"#;
+        assert_eq!(find_anchor_ids(html, "slide-"), vec!["slide-1", "slide-2"]);
+        assert!(find_anchor_ids(html, "chapter-").is_empty());
+    }
+
+    #[test]
+    fn find_element_ids_of_sections() {
+        let html = r#"<section id="intro"><h1>Intro</h1></section>
+<section class="slide"><p>a</p></section>
+<sections id="other"></sections>
+<section data-id="x" id="basics"><p>b</p></section>"#;
+        assert_eq!(
+            find_element_ids(html, "section"),
+            vec![Some("intro".to_string()), None, Some("basics".to_string())]
+        );
+    }
+
+    #[test]
+    fn decode_latin1_text() {
+        assert_eq!(decode_latin1(b"caf\xe9 <b>\xbd</b>"), "café <b>½</b>");
+    }
+
+    #[test]
+    fn file_is_redirect_with_refresh_meta() {
+        let redirect = r#"<!DOCTYPE html>
+<html>
+    <head>
+        <meta charset="utf-8">
+        <title>Redirecting...</title>
+        <meta http-equiv="refresh" content="0; URL=../new.html">
+    </head>
+</html>"#;
+        assert!(file_is_redirect(redirect));
+        assert!(file_is_redirect(
+            "<head><META HTTP-EQUIV=Refresh content=0></head>"
+        ));
+        assert!(!file_is_redirect(
+            "<head><meta charset=\"utf-8\"></head><body>http-equiv=refresh</body>"
+        ));
+    }
+
+    #[test]
+    fn ignore_file_with_comments_and_negation() {
+        let patterns = parse_ignore_file(
+            "# generated pages\n\nprint.html\nexercises/**\n!exercises/keep.html\n",
+        )
+        .unwrap();
+        let ignored = |path: &str| is_ignored(&patterns, Path::new(path));
+        assert!(ignored("print.html"));
+        assert!(ignored("exercises/solution.html"));
+        assert!(!ignored("exercises/keep.html"));
+        assert!(!ignored("index.html"));
+    }
+
+    #[test]
+    fn summary_html_pages_of_chapters() {
+        let summary = r#"# Summary
+
+[Welcome](index.md)
+
+- [Hello](hello.md)
+  - [Nested](hello/nested.md)
+- [Draft]()
+- [Guide](guide/README.md)
+- [External](https://example.com/page.md)"#;
+        assert_eq!(
+            summary_html_pages(summary),
+            vec![
+                Path::new("index.html"),
+                Path::new("hello.html"),
+                Path::new("hello/nested.html"),
+                Path::new("guide/index.html"),
+            ]
+        );
+    }
+
+    #[test]
+    fn common_source_dir_of_multiple_books() {
+        let book = |source_dirs: &[&str]| Book {
+            source_dirs: source_dirs.iter().map(PathBuf::from).collect(),
+            slides: vec![],
+        };
+        assert_eq!(
+            book(&["../book/html"]).common_source_dir(),
+            Path::new("../book/html")
+        );
+        assert_eq!(
+            book(&["/books/a/html", "/books/b/html"]).common_source_dir(),
+            Path::new("/books")
+        );
+        assert_eq!(book(&["a", "b"]).common_source_dir(), Path::new(""));
+    }
+
+    #[test]
+    fn relative_output_path_with_fragment() {
+        let slide = |fragment: Option<&str>| Slide {
+            filename: Path::new("/books/a/html/intro/basics.html").into(),
+            source_dir: Path::new("/books/a/html").into(),
+            fragment: fragment.map(String::from),
+            element_selector: None,
+            content_hash: None,
+        };
+        assert_eq!(
+            slide(None).relative_output_path(Path::new("/books"), "png").unwrap(),
+            Path::new("a/html/intro/basics.png")
+        );
+        assert_eq!(
+            slide(Some("slide-1"))
+                .relative_output_path(Path::new("/books/a/html"), "png")
+                .unwrap(),
+            Path::new("intro/basics.slide-1.png")
+        );
+        let gzipped = Slide {
+            filename: Path::new("/books/a/html/intro/basics.html.gz").into(),
+            ..slide(None)
+        };
+        assert_eq!(
+            gzipped.relative_output_path(Path::new("/books/a/html"), "png").unwrap(),
+            Path::new("intro/basics.png")
+        );
+    }
+
+    #[test]
+    fn slide_id_from_content_hash_and_fragment() {
+        assert_eq!(content_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(content_hash(b"a"), 0xaf63dc4c8601ec8c);
+        let slide = Slide {
+            filename: Path::new("/book/intro.html").into(),
+            source_dir: Path::new("/book").into(),
+            fragment: None,
+            element_selector: None,
+            content_hash: Some(content_hash(b"a")),
+        };
+        assert_eq!(slide.id().as_deref(), Some("af63dc4c8601ec8c"));
+        // a renamed page keeps its id
+        let renamed = Slide {
+            filename: Path::new("/book/welcome.html").into(),
+            ..slide.clone()
+        };
+        assert_eq!(renamed.id(), slide.id());
+        let section = Slide { fragment: Some(String::from("s1")), ..slide };
+        assert_eq!(section.id().as_deref(), Some("af63dc4c8601ec8c#s1"));
+        let unreadable = Slide { content_hash: None, ..section };
+        assert_eq!(unreadable.id(), None);
+    }
+
+    #[test]
+    fn page_content_hash_of_the_main_element() {
+        let page = |sidebar: &str, main: &str| {
+            format!(
+                "<html><body><nav id=\"sidebar\">{sidebar}</nav>\
+                 <div id=\"content\"><main>{main}</main>\
+                 <nav class=\"nav-wrapper\"></nav></div></body></html>"
+            )
+        };
+        let intro = page_content_hash(&page("intro", "<h1>Intro</h1>"));
+        // another chapter in the sidebar does not change the hash
+        assert_eq!(
+            page_content_hash(&page("intro, basics", "<h1>Intro</h1>")),
+            intro
+        );
+        assert_ne!(page_content_hash(&page("intro", "<h1>Welcome</h1>")), intro);
+        // a mainframe element is not the main element
+        let html = "<mainframe>a</mainframe>";
+        assert_eq!(page_content_hash(html), content_hash(html.as_bytes()));
+    }
+
+    #[test]
+    fn compressed_and_uncompressed_pages_are_rejected() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source_dir = temp_dir.path();
+        fs::write(source_dir.join("intro.html"), "<main>intro</main>").unwrap();
+        let mut gzip = flate2::write::GzEncoder::new(
+            fs::File::create(source_dir.join("intro.html.gz")).unwrap(),
+            flate2::Compression::default(),
+        );
+        io::Write::write_all(&mut gzip, b"<main>intro</main>").unwrap();
+        gzip.finish().unwrap();
+        let book = Book::from_html_slides(source_dir.to_path_buf())
+            .unwrap()
+            .with_gzipped_slides();
+
+        let error = book.err().unwrap().to_string();
+        assert!(error.ends_with("intro.html are the same slide, remove one of them"));
+    }
+
+    #[test]
+    fn stale_output_files_of_the_output_layout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let output_dir = dir.join("screenshots");
+        for path in ["book/intro", "screenshots/intro", "screenshots/dir.png"] {
+            fs::create_dir_all(dir.join(path)).unwrap();
+        }
+        for path in [
+            "book/intro/basics.html",
+            "screenshots/intro/basics.png",
+            "screenshots/intro/removed.png",
+            "screenshots/intro__basics.png",
+            "screenshots/removed.png",
+            "screenshots/notes.txt",
+        ] {
+            fs::write(dir.join(path), "").unwrap();
+        }
+        let book = Book::from_html_slides(dir.join("book")).unwrap();
+        let remove_stale_files = |output_layout, dry_run| {
+            let mut stale_files = book
+                .remove_stale_output_files(
+                    &output_dir,
+                    output_layout,
+                    "png",
+                    dry_run,
+                )
+                .map_err(|error| error.to_string())?;
+            stale_files.sort();
+            Ok::<_, String>(stale_files)
+        };
+        let flat = remove_stale_files(OutputLayout::Flat, true);
+        let mirror_dry_run = remove_stale_files(OutputLayout::Mirror, true);
+        let mirror = remove_stale_files(OutputLayout::Mirror, false);
+        let dated = remove_stale_files(OutputLayout::Dated, false);
+        let remaining = glob::glob(&format!("{}/**/*", output_dir.display()))
+            .unwrap()
+            .map(|path| path.unwrap().strip_prefix(&output_dir).unwrap().to_owned())
+            .collect::<Vec<_>>();
+
+        // the flat layout only has files at the top of the output directory
+        assert_eq!(flat.unwrap(), [output_dir.join("removed.png")]);
+        let stale_files = [
+            output_dir.join("intro/removed.png"),
+            output_dir.join("intro__basics.png"),
+            output_dir.join("removed.png"),
+        ];
+        assert_eq!(mirror_dry_run.unwrap(), stale_files);
+        assert_eq!(mirror.unwrap(), stale_files);
+        // other extensions and directories are kept
+        assert_eq!(
+            remaining,
+            ["dir.png", "intro", "intro/basics.png", "notes.txt"].map(PathBuf::from)
+        );
+        assert!(dated.unwrap_err().contains("dated output layout"));
+    }
+
+    #[test]
+    fn unreadable_pages_do_not_abort_the_discovery() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source_dir = temp_dir.path();
+        fs::write(source_dir.join("intro.html"), "<main>intro</main>").unwrap();
+        fs::write(source_dir.join("corrupt.html.gz"), "not gzip").unwrap();
+        let mut discovery = SlideDiscovery::new(vec![source_dir.to_path_buf()]);
+        discovery.gzip = true;
+        let slides = discovery.slides();
+
+        let slides = slides.unwrap();
+        assert_eq!(slides.len(), 2);
+        assert!(slides[0].filename.ends_with("corrupt.html.gz"));
+        assert_eq!(slides[0].content_hash, None);
+        assert_eq!(
+            slides[1].content_hash,
+            Some(content_hash(b"<main>intro</main>"))
+        );
+    }
+
+    #[test]
+    fn read_html_error_names_the_file() {
+        let slide = Slide {
+            filename: Path::new("/nonexistent/book/missing.html").into(),
+            source_dir: Path::new("/nonexistent/book").into(),
+            fragment: None,
+            element_selector: None,
+            content_hash: None,
+        };
+        let error = slide.read_html().unwrap_err();
+        assert!(error.to_string().contains("/nonexistent/book/missing.html"));
+    }
+
+    #[test]
+    fn discover_fixture_slides() {
+        let source_dir =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/book");
+        let mut discovery = SlideDiscovery::new(vec![source_dir]);
+        let slides = discovery.slides().unwrap();
+        let names: Vec<_> =
+            slides.iter().map(|slide| slide.filename.file_name().unwrap()).collect();
+        assert_eq!(names, ["empty.html", "fits.html", "overflows.html"]);
+        assert!(slides.iter().all(|slide| slide.element_selector.is_none()));
+
+        discovery.element_selectors =
+            vec![(glob::Pattern::new("fits.html").unwrap(), String::from("//main"))];
+        let slides = discovery.slides().unwrap();
+        assert_eq!(slides[1].element_selector.as_deref(), Some("//main"));
+        assert_eq!(slides[2].element_selector, None);
+    }
+
+    #[test]
+    fn modified_since_filters_old_slides() {
+        let source_dir =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/book");
+        let book = Book::from_html_slides(source_dir.clone()).unwrap();
+        assert!(book.modified_since(SystemTime::now()).unwrap().slides().is_empty());
+        let book = Book::from_html_slides(source_dir).unwrap();
+        let book = book.modified_since(SystemTime::UNIX_EPOCH).unwrap();
+        assert_eq!(book.slides().len(), 3);
+    }
 }
@@ -0,0 +1,1156 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! end-to-end tests of the evaluator against a stub webdriver. The stub
+//! does not render html, it reports fixed element sizes for the fixture
+//! slides in tests/fixtures/book
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use fantoccini::elements::Element;
+use fantoccini::wd::Capabilities;
+use fantoccini::Client;
+use mdbook_slide_evaluator::estimate::LayoutMetrics;
+use mdbook_slide_evaluator::evaluator::{
+    EvaluationResults, Evaluator, EvaluatorBuilder, NetworkThrottle, RoundingMode,
+    SlidePolicy, Unit, WebFont, WebdriverSession, DEFAULT_ELEMENT_SELECTOR,
+};
+use mdbook_slide_evaluator::metrics::{MetricFuture, NodeCount, SlideMetric};
+use mdbook_slide_evaluator::slides::{Book, SlideDiscovery};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+const SESSION_ID: &str = "stub-session";
+const ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735466cecf";
+
+/// the y coordinate, width and height of the element reported for the
+/// currently opened url. None if the page has no content element. The
+/// fitting slide is pushed down by a header of 100 pixels, a revealed
+/// fragment adds 200 pixels to the height
+fn element_rect(url: &str) -> Option<(f64, f64, f64)> {
+    if let Some(url) = url.strip_suffix("#fragment") {
+        return element_rect(url)
+            .map(|(y, width, height)| (y, width, height + 200.0));
+    }
+    if url.starts_with("data:") {
+        Some((0.0, 640.0, 480.0))
+    } else if url.contains("/sections.html#") {
+        Some((0.0, 700.0, 3000.0))
+    } else if url.ends_with("/fits.html") {
+        Some((100.0, 700.0, 1000.0))
+    } else if url.ends_with("/overflows.html") {
+        Some((0.0, 800.0, 1500.0))
+    } else {
+        None
+    }
+}
+
+/// a png with a single white pixel as the screenshot of every element
+fn screenshot_png() -> Vec<u8> {
+    let image = image::RgbImage::from_pixel(1, 1, image::Rgb([255, 255, 255]));
+    let mut png = std::io::Cursor::new(vec![]);
+    image.write_to(&mut png, image::ImageFormat::Png).unwrap();
+    png.into_inner()
+}
+
+/// the fragment script of the tests, it reveals the single fragment of each
+/// slide
+const NEXT_FRAGMENT_SCRIPT: &str = "return nextFragment()";
+
+/// the name in the first line of the scripts of the evaluator, e.g.
+/// "measure". Scripts provided by the user have none
+fn script_marker(script: &str) -> Option<&str> {
+    script.lines().next()?.strip_prefix("// slide-evaluator: ")
+}
+
+/// the state of the stub webdriver, shared by its connections
+#[derive(Default)]
+struct StubState {
+    /// the url of the opened page
+    current_url: String,
+    /// the arguments of every asynchronous script, in execution order
+    async_script_args: Vec<Value>,
+    /// the timeouts set for the session
+    timeouts: Option<Value>,
+    /// the names of the chrome devtools protocol commands, in execution order
+    cdp_commands: Vec<String>,
+}
+
+/// the prefix of the font families that fail to load in the stub
+const MISSING_FONT_PREFIX: &str = "Missing";
+
+/// answer a webdriver command. Returns the http status and the json body
+fn respond(
+    method: &str,
+    path: &str,
+    body: &Value,
+    state: &Mutex<StubState>,
+) -> (u16, Value) {
+    let mut state = state.lock().unwrap();
+    let current_url = &mut state.current_url;
+    let session = format!("/session/{SESSION_ID}");
+    let command = path.strip_prefix(&session);
+    let value = match (method, command) {
+        ("POST", None) if path == "/session" => json!({
+            "sessionId": SESSION_ID,
+            "capabilities": { "browserName": "stub", "moz:headless": true },
+        }),
+        ("POST", Some("/timeouts")) => {
+            state.timeouts = Some(body.clone());
+            Value::Null
+        }
+        ("POST", Some("/window/rect")) => {
+            json!({ "x": 0, "y": 0, "width": 1920, "height": 1080 })
+        }
+        ("POST", Some("/url")) => {
+            let url = body["url"].as_str().unwrap();
+            if url.ends_with("/unreachable.html") {
+                return (
+                    500,
+                    json!({ "value": {
+                        "error": "unknown error",
+                        "message": "net::ERR_CONNECTION_REFUSED",
+                        "stacktrace": "",
+                    }}),
+                );
+            }
+            *current_url = url.to_string();
+            Value::Null
+        }
+        ("GET", Some("/url")) => json!(current_url),
+        ("POST", Some("/element")) => {
+            if element_rect(current_url).is_none() {
+                return (
+                    404,
+                    json!({ "value": {
+                        "error": "no such element",
+                        "message": "no content element",
+                        "stacktrace": "",
+                    }}),
+                );
+            }
+            json!({ ELEMENT_KEY: "content" })
+        }
+        ("GET", Some("/element/content/screenshot")) => {
+            json!(BASE64_STANDARD.encode(screenshot_png()))
+        }
+        ("GET", Some("/element/content/rect")) => {
+            let (y, width, height) = element_rect(current_url).unwrap();
+            json!({ "x": 0, "y": y, "width": width, "height": height })
+        }
+        // the anchor of a fragment is a heading, its section is long
+        ("POST", Some("/element/content/element")) => {
+            json!({ ELEMENT_KEY: "anchor" })
+        }
+        ("GET", Some("/element/anchor/rect")) => {
+            json!({ "x": 0, "y": 0, "width": 700, "height": 40 })
+        }
+        ("GET", Some("/element/section/rect")) => {
+            json!({ "x": 0, "y": 0, "width": 700, "height": 1800 })
+        }
+        ("POST", Some("/execute/sync")) => {
+            let script = body["script"].as_str().unwrap_or_default();
+            match script_marker(script) {
+                None if script == NEXT_FRAGMENT_SCRIPT => {
+                    // each slide has a single fragment
+                    let revealed = !current_url.ends_with("#fragment");
+                    if revealed {
+                        current_url.push_str("#fragment");
+                    }
+                    json!(revealed)
+                }
+                Some("measure") => {
+                    let (_, width, height) = element_rect(current_url).unwrap();
+                    let args = &body["args"];
+                    // the content elements have a padding of 50 on each side
+                    let content_box = match args[1].as_bool() {
+                        Some(true) => json!([width - 100.0, height - 100.0]),
+                        _ => Value::Null,
+                    };
+                    let dom = match args[2].as_bool() {
+                        Some(true) => {
+                            json!("<html><body><main>rendered</main></body></html>")
+                        }
+                        _ => Value::Null,
+                    };
+                    // the overflowing slide has no navigation timing
+                    let render_time = if current_url.ends_with("/overflows.html") {
+                        Value::Null
+                    } else {
+                        json!(100)
+                    };
+                    let class_attribute = if current_url.ends_with("/overflows.html")
+                    {
+                        "content slide-eval-tall"
+                    } else {
+                        "content"
+                    };
+                    json!({
+                        "pageScrollHeight": 1600,
+                        "devicePixelRatio": 2,
+                        "rootFontSize": 20,
+                        "renderTime": render_time,
+                        "maxCodeLines": 3,
+                        "clippedElements": 1,
+                        "contentBox": content_box,
+                        "classAttribute": class_attribute,
+                        "dom": dom,
+                        "extraCssApplied": Value::Null,
+                    })
+                }
+                Some("fragment-section") => {
+                    assert_eq!(body["args"][0][ELEMENT_KEY], "anchor");
+                    json!({ ELEMENT_KEY: "section" })
+                }
+                Some("node-count") => json!(42),
+                Some("viewport") => json!([1920, 1080]),
+                Some("ready-state") => json!("complete"),
+                Some("element-rect") => {
+                    let (y, width, height) = element_rect(current_url).unwrap();
+                    json!([0, y, width, height])
+                }
+                _ => Value::Null,
+            }
+        }
+        ("POST", Some("/execute/async")) => {
+            let args = body["args"].clone();
+            let failed_families: Vec<&Value> = args[1]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|family| {
+                    family.as_str().is_some_and(|family| {
+                        family.starts_with(MISSING_FONT_PREFIX)
+                    })
+                })
+                .collect();
+            let value = json!(failed_families);
+            state.async_script_args.push(args);
+            value
+        }
+        ("POST", Some("/goog/cdp/execute")) => {
+            state.cdp_commands.push(body["cmd"].as_str().unwrap().to_string());
+            json!({})
+        }
+        ("DELETE", Some("")) => Value::Null,
+        _ => {
+            return (
+                404,
+                json!({ "value": {
+                    "error": "unknown command",
+                    "message": format!("{method} {path}"),
+                    "stacktrace": "",
+                }}),
+            )
+        }
+    };
+    (200, json!({ "value": value }))
+}
+
+/// read a single http request from the connection and answer it
+async fn handle_connection(
+    stream: TcpStream,
+    state: Arc<Mutex<StubState>>,
+) -> anyhow::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).await?;
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+    let (status, response) = respond(&method, &path, &body, &state);
+    let response = response.to_string();
+    let mut stream = reader.into_inner();
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 {status} Stub\r\n\
+                 content-type: application/json; charset=utf-8\r\n\
+                 content-length: {}\r\n\
+                 connection: close\r\n\r\n{response}",
+                response.len()
+            )
+            .as_bytes(),
+        )
+        .await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// start the stub webdriver on a free local port and return its url
+async fn start_stub_webdriver() -> anyhow::Result<String> {
+    Ok(start_stub_webdriver_with_state().await?.0)
+}
+
+/// start the stub webdriver on a free local port and return its url and
+/// its state, to inspect the commands it received
+async fn start_stub_webdriver_with_state(
+) -> anyhow::Result<(String, Arc<Mutex<StubState>>)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let state = Arc::new(Mutex::new(StubState::default()));
+    let shared_state = state.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle_connection(stream, shared_state.clone()));
+        }
+    });
+    Ok((url, state))
+}
+
+fn fixture_book_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/book")
+}
+
+fn stub_evaluator_builder<'a>(webdriver: String) -> EvaluatorBuilder<'a> {
+    Evaluator::builder(
+        WebdriverSession::New { webdriver, capabilities: Capabilities::new() },
+        fixture_book_dir(),
+    )
+    .slide_policy(SlidePolicy::default())
+}
+
+async fn stub_evaluator<'a>() -> anyhow::Result<Evaluator<'a>> {
+    let webdriver = start_stub_webdriver().await?;
+    stub_evaluator_builder(webdriver).build().await
+}
+
+/// evaluate the fixture book with a stub evaluator that is configured by
+/// the closure
+async fn eval_fixture_book(
+    configure: impl FnOnce(EvaluatorBuilder<'static>) -> EvaluatorBuilder<'static>,
+) -> anyhow::Result<EvaluationResults> {
+    let book = Book::from_html_slides(fixture_book_dir())?;
+    let webdriver = start_stub_webdriver().await?;
+    let evaluator = configure(stub_evaluator_builder(webdriver)).build().await?;
+    let results = evaluator.eval_book(book).await?;
+    evaluator.close().await?;
+    Ok(results)
+}
+
+#[test]
+fn estimate_uses_the_element_selectors() -> anyhow::Result<()> {
+    let mut discovery = SlideDiscovery::new(vec![fixture_book_dir()]);
+    discovery.element_selectors =
+        vec![(glob::Pattern::new("overflows.html")?, String::from("//body"))];
+    let estimate = |max_slides| {
+        EvaluationResults::estimate(
+            discovery.book()?,
+            DEFAULT_ELEMENT_SELECTOR,
+            max_slides,
+            &SlidePolicy::default(),
+            &LayoutMetrics::default(),
+        )
+    };
+
+    let results = estimate(None)?;
+    // the empty slide has no content element
+    assert_eq!(results.skipped().len(), 1);
+    let selector_counts: Vec<(String, usize)> = results
+        .selector_counts()
+        .into_iter()
+        .map(|(selector, count)| (selector.to_string(), count))
+        .collect();
+    assert_eq!(
+        selector_counts,
+        vec![
+            (String::from("//*[@id=\"content\"]/main"), 1),
+            (String::from("//body"), 1)
+        ]
+    );
+
+    let results = estimate(Some(1))?;
+    assert_eq!(results.skipped().len() + results.selector_counts().len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn sections_of_heading_anchors_are_measured() -> anyhow::Result<()> {
+    let book_dir = tempfile::tempdir()?;
+    std::fs::write(
+        book_dir.path().join("sections.html"),
+        r#"<div id="content"><main>
+<h2 id="slide-1">One</h2><p>a long section</p>
+<h2 id="slide-2">Two</h2><p>another long section</p>
+</main></div>"#,
+    )?;
+    let mut discovery = SlideDiscovery::new(vec![book_dir.path().to_path_buf()]);
+    discovery.anchor_prefix = Some(String::from("slide-"));
+    let webdriver = start_stub_webdriver().await?;
+    let evaluator = Evaluator::builder(
+        WebdriverSession::New { webdriver, capabilities: Capabilities::new() },
+        book_dir.path().to_path_buf(),
+    )
+    .slide_policy(SlidePolicy::default())
+    .build()
+    .await?;
+    let results = evaluator.eval_book(discovery.book()?).await?;
+    evaluator.close().await?;
+
+    let mut jsonl = vec![];
+    results.export_jsonl(&mut jsonl, false, RoundingMode::None, false)?;
+    let records: Vec<Value> = String::from_utf8(jsonl)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(records.len(), 2);
+    for record in records {
+        // the section, not only the heading of the anchor
+        assert_eq!(record["element_height"], 1800.0);
+        assert_eq!(record["policy_violations"], "MaxHeight");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn slides_that_cannot_be_opened_are_skipped() -> anyhow::Result<()> {
+    let book_dir = tempfile::tempdir()?;
+    for page in ["fits.html", "unreachable.html"] {
+        std::fs::write(book_dir.path().join(page), "<main>slide</main>")?;
+    }
+    let book = Book::from_html_slides(book_dir.path().to_path_buf())?;
+    let webdriver = start_stub_webdriver().await?;
+    let evaluator = Evaluator::builder(
+        WebdriverSession::New { webdriver, capabilities: Capabilities::new() },
+        book_dir.path().to_path_buf(),
+    )
+    .slide_policy(SlidePolicy::default())
+    .build()
+    .await?;
+    let results = evaluator.eval_book(book).await?;
+    evaluator.close().await?;
+
+    let skipped = results.skipped();
+    assert_eq!(skipped.len(), 1);
+    assert!(skipped[0].slide.filename.ends_with("unreachable.html"));
+    assert_eq!(skipped[0].reason.to_string(), "NavigationFailed");
+    let error = skipped[0].error.as_deref().unwrap_or_default();
+    assert!(error.contains("net::ERR_CONNECTION_REFUSED"), "{error}");
+    // the other slides are still measured
+    assert_eq!(first_record(&results)?["element_height"], 1000.0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failure_banner_groups_failed_and_skipped_slides() -> anyhow::Result<()> {
+    let results = eval_fixture_book(|builder| builder).await?;
+    assert_eq!(
+        results.failure_banner().as_deref(),
+        Some(
+            "1 slide overflows vertically, 1 slide overflows horizontally, \
+             1 skipped (no content)"
+        )
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn eval_book_measures_fixture_slides() -> anyhow::Result<()> {
+    let results = eval_fixture_book(|builder| builder).await?;
+
+    // the slide without a content element is skipped
+    assert_eq!(results.skipped().len(), 1);
+    assert!(results.skipped()[0].slide.filename.ends_with("empty.html"));
+    assert_eq!(results.failure_count(), 1);
+
+    let status_dir = tempfile::tempdir()?;
+    let status_file = status_dir.path().join("status.json");
+    let mut status = results.run_status();
+    status.exit_code = 1;
+    status.export(&status_file)?;
+    let status: Value = serde_json::from_slice(&std::fs::read(&status_file)?)?;
+    assert_eq!(
+        status,
+        json!({ "passed": 1, "failed": 1, "skipped": 1, "ok": false, "exit_code": 1 })
+    );
+
+    // the fixture slides are at the top level, each is its own chapter
+    let chapters = results.chapter_summaries();
+    assert_eq!(chapters.len(), 2);
+    assert_eq!(chapters[1].chapter, "overflows.html");
+    assert_eq!(chapters[1].failures, 1);
+    assert_eq!(chapters[1].worst_fill_percent, 113.0);
+
+    let mut jsonl = vec![];
+    results.export_jsonl(&mut jsonl, false, RoundingMode::None, false)?;
+    let mut records = String::from_utf8(jsonl)?
+        .lines()
+        .map(serde_json::from_str::<Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+    records.sort_by_key(|record| record["filename"].to_string());
+    assert_eq!(records.len(), 2);
+
+    let fits = &records[0];
+    assert!(fits["filename"].as_str().unwrap().ends_with("fits.html"));
+    assert_eq!(fits["element_width"], 700.0);
+    assert_eq!(fits["element_height"], 1000.0);
+    assert_eq!(fits["page_scroll_height"], 1600.0);
+    assert_eq!(fits["max_code_lines"], 3);
+    assert_eq!(fits["clipped_elements"], 1);
+    assert_eq!(fits["policy_violations"], "");
+
+    let overflows = &records[1];
+    assert!(overflows["filename"].as_str().unwrap().ends_with("overflows.html"));
+    assert_eq!(overflows["element_width"], 800.0);
+    assert_eq!(overflows["element_height"], 1500.0);
+    assert_eq!(overflows["horizontal_overflow"], 50.0);
+    assert_eq!(overflows["vertical_overflow"], 167.0);
+    assert_eq!(overflows["policy_violations"], "MaxHeight;MaxWidth");
+
+    // only the overflowing slide is exported with violations_only
+    let mut jsonl = vec![];
+    results.export_jsonl(&mut jsonl, true, RoundingMode::None, false)?;
+    let jsonl = String::from_utf8(jsonl)?;
+    assert_eq!(jsonl.lines().count(), 1);
+    assert!(jsonl.contains("overflows.html"));
+
+    // the stub reports a device pixel ratio of 2, the dimensions are css
+    // pixels nevertheless
+    let fits = first_record(&results)?;
+    assert!(fits["filename"].as_str().unwrap().ends_with("fits.html"));
+    assert_eq!(fits["device_pixel_ratio"], 2.0);
+    assert_eq!(fits["element_width"], 700.0);
+    assert_eq!(fits["element_height"], 1000.0);
+
+    // the stub reports a root font size of 20px
+    let results = results.with_unit(Unit::Rem);
+    let fits = first_record(&results)?;
+    assert_eq!(fits["unit"], "rem");
+    assert_eq!(fits["element_width"], 35.0);
+    assert_eq!(fits["element_height"], 50.0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn threshold_from_baseline_flags_outliers() -> anyhow::Result<()> {
+    let results = eval_fixture_book(|builder| {
+        builder.slide_policy(SlidePolicy {
+            failure_message: Some(String::from("{height} is above {max_height}")),
+            ..SlidePolicy::default()
+        })
+    })
+    .await?;
+
+    assert_eq!(results.height_percentile(50.0), Some(1000.0));
+    assert_eq!(results.height_percentile(100.0), Some(1500.0));
+
+    // the higher slide is an outlier instead of exceeding the max height.
+    // evaluating it again does not add the outlier twice
+    let results = results
+        .with_height_threshold_from_baseline(50.0)
+        .with_height_threshold_from_baseline(50.0);
+    let outlier = |results: &EvaluationResults| -> anyhow::Result<Value> {
+        let mut jsonl = vec![];
+        results.export_jsonl(&mut jsonl, false, RoundingMode::None, false)?;
+        let jsonl = String::from_utf8(jsonl)?;
+        Ok(serde_json::from_str(jsonl.lines().nth(1).unwrap())?)
+    };
+    let record = outlier(&results)?;
+    assert!(record["filename"].as_str().unwrap().ends_with("overflows.html"));
+    assert_eq!(record["policy_violations"], "HeightOutlier;MaxWidth");
+    assert_eq!(record["vertical_overflow"], 500.0);
+    assert_eq!(record["fill_percent"], 150.0);
+    assert_eq!(record["severity"], "Fail");
+    assert_eq!(record["failure_message"], "1500 is above 1000");
+    // the fitting slide fills the derived max height
+    let fits = first_record(&results)?;
+    assert_eq!(fits["policy_violations"], "");
+    assert_eq!(fits["fill_percent"], 100.0);
+    assert_eq!(fits["severity"], "Warn");
+
+    // every slide is within the 100th percentile, the higher slide is only
+    // too wide
+    let results = results.with_height_threshold_from_baseline(100.0);
+    let record = outlier(&results)?;
+    assert_eq!(record["policy_violations"], "MaxWidth");
+    assert_eq!(record["vertical_overflow"], 0.0);
+    assert_eq!(record["failure_message"], "1500 is above 1500");
+    assert_eq!(first_record(&results)?["policy_violations"], "");
+    Ok(())
+}
+
+#[tokio::test]
+async fn identical_sizes_are_detected() -> anyhow::Result<()> {
+    let results = eval_fixture_book(|builder| builder).await?;
+
+    // each of the two measured slides has its own size
+    assert_eq!(results.identical_size(2, 0.5), Some(((700.0, 1000.0), 1)));
+    assert_eq!(results.identical_size(2, 0.6), None);
+    assert_eq!(results.identical_size(3, 0.5), None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn merge_replaces_the_slides_of_another_checkout() -> anyhow::Result<()> {
+    // a copy of the book at another location, e.g. the checkout of a shard
+    let checkout_dir = tempfile::tempdir()?;
+    let checkout = checkout_dir.path().to_path_buf();
+    for entry in std::fs::read_dir(fixture_book_dir())? {
+        let entry = entry?;
+        std::fs::copy(entry.path(), checkout.join(entry.file_name()))?;
+    }
+    let results = eval_fixture_book(|builder| builder).await?;
+    let webdriver = start_stub_webdriver().await?;
+    let evaluator = Evaluator::builder(
+        WebdriverSession::New { webdriver, capabilities: Capabilities::new() },
+        checkout.clone(),
+    )
+    .slide_policy(SlidePolicy::default())
+    .build()
+    .await?;
+    let other =
+        evaluator.eval_book(Book::from_html_slides(checkout.clone())?).await?;
+    evaluator.close().await?;
+
+    let merged = results.merge(other);
+    assert_eq!(merged.skipped().len(), 1);
+    assert!(merged.skipped()[0].slide.filename.starts_with(&checkout));
+    let mut jsonl = vec![];
+    merged.export_jsonl(&mut jsonl, false, RoundingMode::None, false)?;
+    let records = String::from_utf8(jsonl)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<Value>, _>>()?;
+    assert_eq!(records.len(), 2);
+    assert!(records
+        .iter()
+        .all(|record| record["source_dir"] == checkout.to_str().unwrap()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn screenshots_are_indexed() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let screenshot_dir = temp_dir.path().to_path_buf();
+    let results = eval_fixture_book(|builder| {
+        builder.screenshot_dir(Some(screenshot_dir.clone()))
+    })
+    .await?;
+
+    assert_eq!(results.export_screenshot_index(&screenshot_dir)?, 2);
+    let index: Value =
+        serde_json::from_slice(&std::fs::read(screenshot_dir.join("index.json"))?)?;
+    assert_eq!(
+        index,
+        json!({ "fits.html": "fits.png", "overflows.html": "overflows.png" })
+    );
+    assert!(!screenshot_dir.join("index.json.tmp").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn screenshot_size_is_limited() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let screenshot_dir = temp_dir.path().to_path_buf();
+    let results = eval_fixture_book(|builder| {
+        builder.screenshot_dir(Some(screenshot_dir.clone())).slide_policy(
+            SlidePolicy { max_screenshot_size: Some(10), ..SlidePolicy::default() },
+        )
+    })
+    .await?;
+
+    // the png of a single pixel is larger than 10 bytes
+    let fits = first_record(&results)?;
+    assert_eq!(fits["policy_violations"], "ScreenshotSize");
+    Ok(())
+}
+
+#[tokio::test]
+async fn results_are_exported_incrementally() -> anyhow::Result<()> {
+    let export_dir = tempfile::tempdir()?;
+    let incremental_export = export_dir.path().join("results.jsonl");
+    let results = eval_fixture_book(|builder| {
+        builder.incremental_export(Some(incremental_export.clone()))
+    })
+    .await?;
+
+    // the file has the same records as the final export
+    let mut jsonl = vec![];
+    results.export_jsonl(&mut jsonl, false, RoundingMode::None, false)?;
+    assert_eq!(std::fs::read(&incremental_export)?, jsonl);
+    Ok(())
+}
+
+#[tokio::test]
+async fn fragments_are_measured() -> anyhow::Result<()> {
+    let results = eval_fixture_book(|builder| {
+        builder.fragment_script(Some(String::from(NEXT_FRAGMENT_SCRIPT)))
+    })
+    .await?;
+
+    let fits = first_record(&results)?;
+    assert_eq!(fits["fragment_steps"], 1);
+    assert_eq!(fits["element_height"], 1200.0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn slides_with_fragments_can_be_stable() -> anyhow::Result<()> {
+    let results = eval_fixture_book(|builder| {
+        builder
+            .fragment_script(Some(String::from(NEXT_FRAGMENT_SCRIPT)))
+            .stability_tolerance(Some(1.0))
+    })
+    .await?;
+
+    // the second measurement reveals the fragment again
+    let fits = first_record(&results)?;
+    assert_eq!(fits["element_height"], 1200.0);
+    assert_eq!(fits["unstable"], false);
+    Ok(())
+}
+
+#[tokio::test]
+async fn regressions_from_baseline_compare_dimensions() -> anyhow::Result<()> {
+    let results = eval_fixture_book(|builder| builder).await?;
+
+    // a baseline from another checkout with different screenshots
+    let baseline = "source_dir,filename,fragment,element_width,element_height,\
+                    page_scroll_height,screenshot
+/other/book,/other/book/fits.html,,700,990,1600,other.png
+/other/book,/other/book/overflows.html,,800,1500,1600,other.png
+";
+    let regressions = results.regressions_from_baseline(baseline.as_bytes(), 5.0)?;
+    assert_eq!(regressions.len(), 1);
+    assert_eq!(
+        regressions[0].to_string(),
+        "fits.html: element_height grew from 990 to 1000"
+    );
+    assert!(results
+        .regressions_from_baseline(baseline.as_bytes(), 10.0)?
+        .is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn rendered_dom_is_stored() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let dom_dump_dir = temp_dir.path().to_path_buf();
+    eval_fixture_book(|builder| builder.dom_dump_dir(Some(dom_dump_dir.clone())))
+        .await?;
+
+    assert_eq!(
+        std::fs::read_to_string(dom_dump_dir.join("fits.html"))?,
+        "<html><body><main>rendered</main></body></html>"
+    );
+    // the slide without a content element is not measured
+    assert!(!dom_dump_dir.join("empty.html").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn classes_override_the_max_height() -> anyhow::Result<()> {
+    let results = eval_fixture_book(|builder| {
+        builder.slide_policy(SlidePolicy {
+            class_max_heights: vec![(String::from("slide-eval-tall"), 2000)],
+            ..SlidePolicy::default()
+        })
+    })
+    .await?;
+
+    // the overflowing slide is only too wide with the higher maximum height
+    let mut jsonl = vec![];
+    results.export_jsonl(&mut jsonl, true, RoundingMode::None, false)?;
+    let record: Value = serde_json::from_slice(&jsonl)?;
+    assert!(record["filename"].as_str().unwrap().ends_with("overflows.html"));
+    assert_eq!(record["policy_violations"], "MaxWidth");
+    assert_eq!(record["max_height_override"], 2000);
+    assert_eq!(record["vertical_overflow"], 0.0);
+    assert_eq!(first_record(&results)?["max_height_override"], Value::Null);
+
+    // the height threshold of the book does not replace the maximum height
+    // of the class
+    let results = results.with_height_threshold_from_baseline(50.0);
+    let mut jsonl = vec![];
+    results.export_jsonl(&mut jsonl, true, RoundingMode::None, false)?;
+    let record: Value = serde_json::from_slice(&jsonl)?;
+    assert_eq!(record["policy_violations"], "MaxWidth");
+    assert_eq!(record["vertical_overflow"], 0.0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn fonts_are_loaded_before_measuring() -> anyhow::Result<()> {
+    let font = WebFont {
+        family: String::from("Open Sans"),
+        url: String::from("https://host/fonts/open-sans.woff2"),
+    };
+    let book = Book::from_html_slides(fixture_book_dir())?;
+    let (webdriver, state) = start_stub_webdriver_with_state().await?;
+    let evaluator =
+        stub_evaluator_builder(webdriver).fonts(vec![font]).build().await?;
+    let results = evaluator.eval_book(book).await?;
+    evaluator.close().await?;
+
+    assert_eq!(results.failure_count(), 1);
+    assert_eq!(first_record(&results)?["element_height"], 1000.0);
+    // the fonts are loaded once for each of the three slides, before the
+    // content element is looked up
+    let async_script_args = &state.lock().unwrap().async_script_args;
+    assert_eq!(async_script_args.len(), 3);
+    for args in async_script_args {
+        assert_eq!(
+            args[0],
+            "@font-face { font-family: \"Open Sans\"; \
+             src: url(\"https://host/fonts/open-sans.woff2\"); \
+             font-display: block; }\n"
+        );
+        assert_eq!(args[1], json!(["Open Sans"]));
+    }
+    Ok(())
+}
+
+/// a logger that keeps the messages of all tests, to check the warnings
+struct CapturingLogger;
+
+static LOG_MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+impl log::Log for CapturingLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let mut messages =
+            LOG_MESSAGES.lock().unwrap_or_else(PoisonError::into_inner);
+        messages.push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+/// install the capturing logger once for the whole test binary
+fn capture_log_messages() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&CapturingLogger).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+    });
+}
+
+#[tokio::test]
+async fn timeouts_are_enforced_by_the_browser() -> anyhow::Result<()> {
+    let (webdriver, state) = start_stub_webdriver_with_state().await?;
+    let evaluator = stub_evaluator_builder(webdriver.clone()).build().await?;
+    evaluator.close().await?;
+    assert_eq!(state.lock().unwrap().timeouts, None);
+
+    let evaluator = stub_evaluator_builder(webdriver)
+        .navigation_timeout(Some(std::time::Duration::from_secs(5)))
+        .find_timeout(Some(std::time::Duration::from_millis(200)))
+        .build()
+        .await?;
+    evaluator.close().await?;
+    // the navigation timeout covers loading the page and the scripts
+    assert_eq!(
+        state.lock().unwrap().timeouts,
+        Some(json!({ "pageLoad": 5000, "script": 5000, "implicit": 200 }))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_fonts_are_reported() -> anyhow::Result<()> {
+    capture_log_messages();
+    let fonts = vec![
+        WebFont {
+            family: String::from("Open Sans"),
+            url: String::from("https://host/fonts/open-sans.woff2"),
+        },
+        WebFont {
+            family: format!("{MISSING_FONT_PREFIX} Sans"),
+            url: String::from("https://host/fonts/missing-sans.woff2"),
+        },
+    ];
+    let results = eval_fixture_book(|builder| builder.fonts(fonts)).await?;
+
+    // a font that does not load is a warning, the slides are still measured
+    assert_eq!(results.failure_count(), 1);
+    let font_warnings: Vec<String> = LOG_MESSAGES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .filter(|message| message.starts_with("font "))
+        .cloned()
+        .collect();
+    for slide in ["fits.html", "overflows.html"] {
+        assert!(
+            font_warnings.iter().any(|message| message
+                .starts_with("font \"Missing Sans\" is not loaded")
+                && message.contains(slide)),
+            "no font warning for {slide} in {font_warnings:?}"
+        );
+    }
+    assert!(font_warnings.iter().all(|message| message.contains("Missing Sans")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn explanation_names_thresholds_and_violations() -> anyhow::Result<()> {
+    let results = eval_fixture_book(|builder| builder).await?;
+
+    let mut explanation = vec![];
+    results.export_explanation(&mut explanation, true)?;
+    let explanation = String::from_utf8(explanation)?;
+    let lines: Vec<&str> = explanation.lines().collect();
+    assert!(lines[0].ends_with("overflows.html: Fail"));
+    assert_eq!(
+        lines[1..],
+        [
+            "  measured 800x1500, page height 1600",
+            "  maximum 750x1333, fails above 100% (750x1333), warns from 90%",
+            "  fills 113% of the maximum size",
+            "  MaxHeight: height 1500 is above 1333",
+            "  MaxWidth: width 800 is above 750",
+            "  fails because of the violations above",
+        ]
+    );
+
+    // the explanation names the derived height threshold of the verdict
+    let results = results.with_height_threshold_from_baseline(50.0);
+    let mut explanation = vec![];
+    results.export_explanation(&mut explanation, true)?;
+    let explanation = String::from_utf8(explanation)?;
+    let lines: Vec<&str> = explanation.lines().collect();
+    assert_eq!(
+        lines[2..5],
+        [
+            "  maximum 750x1000 (the 50th percentile of the slide heights), \
+             fails above 100% (750x1000), warns from 90%",
+            "  fills 150% of the maximum size",
+            "  HeightOutlier: height 1500 is above 1000, the height threshold of \
+             the book",
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn throttling_is_exported() -> anyhow::Result<()> {
+    let (webdriver, state) = start_stub_webdriver_with_state().await?;
+    let evaluator = stub_evaluator_builder(webdriver)
+        .reduced_motion(true)
+        .cpu_throttle(Some(4.0))
+        .network_throttle(Some(NetworkThrottle::Slow3g))
+        .build()
+        .await?;
+    let results =
+        evaluator.eval_book(Book::from_html_slides(fixture_book_dir())?).await?;
+    evaluator.close().await?;
+
+    let cdp_commands = state.lock().unwrap().cdp_commands.clone();
+    assert!(cdp_commands.contains(&String::from("Emulation.setCPUThrottlingRate")));
+    assert!(cdp_commands.contains(&String::from("Network.emulateNetworkConditions")));
+    let fits = first_record(&results)?;
+    assert_eq!(fits["reduced_motion"], true);
+    assert_eq!(fits["cpu_throttle"], 4.0);
+    assert_eq!(fits["network_throttle"], "slow3g");
+
+    // unthrottled runs are told apart by the empty fields
+    let fits = first_record(&eval_fixture_book(|builder| builder).await?)?;
+    assert_eq!(fits["cpu_throttle"], Value::Null);
+    assert_eq!(fits["network_throttle"], Value::Null);
+    Ok(())
+}
+
+#[tokio::test]
+async fn sql_export_inserts_the_run() -> anyhow::Result<()> {
+    let export_dir = tempfile::tempdir()?;
+    let sql_file = export_dir.path().join("results.sql");
+    let results = eval_fixture_book(|builder| builder)
+        .await?
+        .with_label(Some(String::from("it's")));
+
+    let timestamp = std::time::UNIX_EPOCH + std::time::Duration::from_secs(86400);
+    results.export_sql(&sql_file, false, "run-1", timestamp)?;
+    assert!(results.export_sql(&sql_file, false, "run-1", timestamp).is_err());
+    let sql = std::fs::read_to_string(&sql_file)?;
+    assert!(sql.starts_with("CREATE TABLE IF NOT EXISTS runs ("));
+    assert!(sql.contains(
+        "CREATE UNIQUE INDEX IF NOT EXISTS slide_results_slide\n    \
+         ON slide_results (run_id, filename, IFNULL(fragment, ''));"
+    ));
+    assert!(sql.contains(
+        "INSERT OR IGNORE INTO runs VALUES \
+         ('run-1', 'it''s', '1970-01-02T00:00:00Z', 2, 1);"
+    ));
+    let overflows =
+        sql.lines().find(|line| line.contains("'overflows.html'")).unwrap();
+    assert!(overflows.ends_with(
+        "'overflows.html', NULL, 800, 1500, 1600, 3, NULL, 'Fail', 'MaxHeight;MaxWidth');"
+    ));
+    assert!(sql.ends_with("COMMIT;\n"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn render_time_is_unavailable_without_navigation_timing() -> anyhow::Result<()>
+{
+    let results = eval_fixture_book(|builder| {
+        builder.slide_policy(SlidePolicy {
+            max_render_time: Some(std::time::Duration::from_millis(50)),
+            ..SlidePolicy::default()
+        })
+    })
+    .await?;
+
+    let mut jsonl = vec![];
+    results.export_jsonl(&mut jsonl, false, RoundingMode::None, false)?;
+    let records = String::from_utf8(jsonl)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<Value>, _>>()?;
+    let record = |filename: &str| {
+        records
+            .iter()
+            .find(|record| record["filename"].as_str().unwrap().ends_with(filename))
+            .unwrap()
+    };
+    let fits = record("fits.html");
+    assert_eq!(fits["render_time_ms"], 100);
+    assert_eq!(fits["policy_violations"], "RenderTime");
+    // no render time is not a violation of the maximum render time
+    let overflows = record("overflows.html");
+    assert_eq!(overflows["render_time_ms"], Value::Null);
+    assert_eq!(overflows["policy_violations"], "MaxHeight;MaxWidth");
+    Ok(())
+}
+
+#[tokio::test]
+async fn content_box_is_measured() -> anyhow::Result<()> {
+    let results =
+        eval_fixture_book(|builder| builder.measure_content_box(true)).await?;
+
+    let fits = first_record(&results)?;
+    assert_eq!(fits["element_width"], 700.0);
+    assert_eq!(fits["content_width"], 600.0);
+    assert_eq!(fits["element_height"], 1000.0);
+    assert_eq!(fits["content_height"], 900.0);
+
+    // the content box is not measured by default
+    let results = eval_fixture_book(|builder| builder).await?;
+    assert_eq!(first_record(&results)?["content_width"], Value::Null);
+    Ok(())
+}
+
+#[tokio::test]
+async fn content_below_the_fold_fails() -> anyhow::Result<()> {
+    // the fitting slide starts at y 100 and ends at 1100, the overflowing
+    // slide starts at 0 and ends at 1500
+    let results = eval_fixture_book(|builder| {
+        builder
+            .slide_policy(SlidePolicy { fold: Some(1050), ..SlidePolicy::default() })
+    })
+    .await?;
+
+    let fits = first_record(&results)?;
+    assert_eq!(fits["policy_violations"], "BelowFold");
+    let mut explanation = vec![];
+    results.export_explanation(&mut explanation, false)?;
+    let explanation = String::from_utf8(explanation)?;
+    assert!(
+        explanation.contains("  BelowFold: bottom 1100 is below the fold at 1050\n")
+    );
+    assert!(
+        explanation.contains("  BelowFold: bottom 1500 is below the fold at 1050\n")
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn custom_metrics_are_exported() -> anyhow::Result<()> {
+    let metrics: Vec<Box<dyn SlideMetric>> =
+        vec![Box::new(NodeCount), Box::new(ElementWidth)];
+    let results = eval_fixture_book(|builder| builder.metrics(metrics)).await?;
+
+    let fits = first_record(&results)?;
+    assert_eq!(fits["metrics"], "node_count=42;element_width=700");
+    Ok(())
+}
+
+#[tokio::test]
+async fn eval_html_returns_element_size() -> anyhow::Result<()> {
+    let evaluator = stub_evaluator().await?;
+    let element_size = evaluator
+        .eval_html("<div id=\"content\"><main>slide</main></div>")
+        .await?
+        .expect("content element");
+    evaluator.close().await?;
+
+    assert_eq!(element_size.width, 640.0);
+    assert_eq!(element_size.height, 480.0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn script_measurement_returns_element_size() -> anyhow::Result<()> {
+    let webdriver = start_stub_webdriver().await?;
+    let evaluator =
+        stub_evaluator_builder(webdriver).script_measurement(true).build().await?;
+    let element_size = evaluator
+        .eval_html("<div id=\"content\"><main>slide</main></div>")
+        .await?
+        .expect("content element");
+    evaluator.close().await?;
+
+    assert_eq!(element_size.width, 640.0);
+    assert_eq!(element_size.height, 480.0);
+    Ok(())
+}
+
+/// the first exported json record of the results without rounding
+fn first_record(results: &EvaluationResults) -> anyhow::Result<Value> {
+    let mut jsonl = vec![];
+    results.export_jsonl(&mut jsonl, false, RoundingMode::None, false)?;
+    let jsonl = String::from_utf8(jsonl)?;
+    Ok(serde_json::from_str(jsonl.lines().next().unwrap())?)
+}
+
+/// a custom metric that reports the width of the element
+struct ElementWidth;
+
+impl SlideMetric for ElementWidth {
+    fn name(&self) -> &str {
+        "element_width"
+    }
+
+    fn measure<'a>(
+        &'a self,
+        _webclient: &'a Client,
+        element: &'a Element,
+    ) -> MetricFuture<'a> {
+        Box::pin(async move { Ok(element.rectangle().await?.2) })
+    }
+}